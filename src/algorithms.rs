@@ -1,19 +1,28 @@
+use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
 
 use crate::cell;
+use crate::graph;
 use crate::grid;
 use crate::hash_grid;
+use crate::spanning;
 
 pub fn binary_tree_cell(some_cell: &mut cell::Cell) -> &mut cell::Cell {
+    binary_tree_cell_with_rng(some_cell, &mut thread_rng())
+}
+
+pub fn binary_tree_cell_with_rng<'a, R: Rng + ?Sized>(
+    some_cell: &'a mut cell::Cell,
+    rng: &mut R,
+) -> &'a mut cell::Cell {
     let mut neighbors: Vec<(i32, i32)> = vec![];
-    if some_cell.north.is_some() {
-        neighbors.push(some_cell.north.expect("should be present"));
+    if let Some(north) = some_cell.north {
+        neighbors.push(north);
     }
-    if some_cell.east.is_some() {
-        neighbors.push(some_cell.east.expect("This shouldn't happen"));
+    if let Some(east) = some_cell.east {
+        neighbors.push(east);
     }
     if !neighbors.is_empty() {
-        let mut rng = thread_rng();
         let pick = rng.gen_range(0..neighbors.len());
         let coords = neighbors[pick];
         some_cell.link(coords);
@@ -22,11 +31,15 @@ pub fn binary_tree_cell(some_cell: &mut cell::Cell) -> &mut cell::Cell {
 }
 
 pub fn binary_tree(some_grid: &grid::Grid) -> grid::Grid {
+    binary_tree_with_rng(some_grid, &mut thread_rng())
+}
+
+pub fn binary_tree_with_rng<R: Rng + ?Sized>(some_grid: &grid::Grid, rng: &mut R) -> grid::Grid {
     let mut outer: Vec<Vec<cell::Cell>> = Vec::new();
     for row in some_grid.each_row() {
         let mut inner: Vec<cell::Cell> = Vec::new();
         for mut cll in row.clone() {
-            let new_cell = binary_tree_cell(&mut cll);
+            let new_cell = binary_tree_cell_with_rng(&mut cll, rng);
             inner.push(new_cell.clone());
         }
         outer.push(inner);
@@ -35,11 +48,14 @@ pub fn binary_tree(some_grid: &grid::Grid) -> grid::Grid {
 }
 
 pub fn sidewinder(some_grid: &grid::Grid) -> grid::Grid {
+    sidewinder_with_rng(some_grid, &mut thread_rng())
+}
+
+pub fn sidewinder_with_rng<R: Rng + ?Sized>(some_grid: &grid::Grid, rng: &mut R) -> grid::Grid {
     let mut outer: Vec<Vec<cell::Cell>> = Vec::new();
     for row in some_grid.each_row() {
         let mut inner: Vec<cell::Cell> = Vec::new();
         let mut run_count = 1i32;
-        let mut rng = thread_rng();
 
         for (col_num, cll) in row.iter().enumerate() {
             inner.push(cll.clone());
@@ -49,15 +65,14 @@ pub fn sidewinder(some_grid: &grid::Grid) -> grid::Grid {
 
             if should_close_out {
                 let idx = if run_count == 1 {
-                    col_num as usize
+                    col_num
                 } else {
                     rng.gen_range(col_num + 1 - (run_count as usize)..col_num + 1)
                 };
                 run_count = 1;
 
                 let member = &inner[idx];
-                if member.north.is_some() {
-                    let north_cell = member.north.expect("should be present");
+                if let Some(north_cell) = member.north {
                     let mut new_member = member.clone();
                     new_member.link(north_cell);
                     inner[idx] = new_member;
@@ -66,7 +81,7 @@ pub fn sidewinder(some_grid: &grid::Grid) -> grid::Grid {
                 let east_cell = cll.east.expect("should be some");
                 let mut new_cll = cll.clone();
                 new_cll.link(east_cell);
-                inner[col_num as usize] = new_cll;
+                inner[col_num] = new_cll;
                 run_count += 1;
             }
         }
@@ -77,20 +92,302 @@ pub fn sidewinder(some_grid: &grid::Grid) -> grid::Grid {
 
 // These algorithms only work on HashGrids: it was too hard to get them working with others
 pub fn aldous_broder(hgrid: &mut hash_grid::HashGrid) -> &mut hash_grid::HashGrid {
+    aldous_broder_with_rng(hgrid, &mut thread_rng())
+}
+
+pub fn aldous_broder_with_rng<'a, R: Rng + ?Sized>(
+    hgrid: &'a mut hash_grid::HashGrid,
+    rng: &mut R,
+) -> &'a mut hash_grid::HashGrid {
     let mut unvisited = hgrid.len() - 1;
-    let mut cll = hgrid.random_cell().unwrap().clone();
+    let mut current = hgrid.random_cell_with_rng(rng).unwrap().coords();
     while unvisited > 0 {
-        let neighbor = cll.random_neighbor().unwrap();
-        let mut ncell = hgrid.get_item((neighbor.0, neighbor.1)).unwrap().clone();
-        if !ncell.has_links() {
-            cll.link((ncell.row, ncell.column));
-            ncell.link((cll.row, cll.column));
-            let _ = hgrid.replace_cell(cll);
-            let _ = hgrid.replace_cell(ncell.clone());
+        let neighbor = *hgrid
+            .get_item(current)
+            .unwrap()
+            .random_neighbor_with_rng(rng)
+            .unwrap();
+        if !hgrid.get_item(neighbor).unwrap().has_links() {
+            hgrid.link_cells(current, neighbor);
             unvisited -= 1;
         }
-        cll = ncell;
+        current = neighbor;
     }
 
     hgrid
 }
+
+// Braiding removes dead ends by linking them to a neighbor, which adds loops to a perfect maze.
+// Each dead end is removed with probability `p`, from 0 to 1, preferring neighbors which are also
+// dead ends.
+pub fn braid(hgrid: &mut hash_grid::HashGrid, p: f64) -> &mut hash_grid::HashGrid {
+    braid_with_rng(hgrid, p, &mut thread_rng())
+}
+
+pub fn braid_with_rng<'a, R: Rng + ?Sized>(
+    hgrid: &'a mut hash_grid::HashGrid,
+    p: f64,
+    rng: &mut R,
+) -> &'a mut hash_grid::HashGrid {
+    let mut deadends = hgrid.deadends();
+    deadends.shuffle(rng);
+
+    for coords in deadends {
+        let cll = hgrid.get_item(coords).unwrap();
+        // an earlier pass may have already linked this one
+        if cll.links.len() != 1 || !rng.gen_bool(p) {
+            continue;
+        }
+        let neighbors: Vec<(i32, i32)> = cll
+            .neighbors()
+            .into_iter()
+            .filter(|neighbor| !cll.links.contains(*neighbor))
+            .copied()
+            .collect();
+        let best: Vec<(i32, i32)> = neighbors
+            .iter()
+            .filter(|neighbor| {
+                hgrid
+                    .get_item(**neighbor)
+                    .map(|ncell| ncell.links.len() == 1)
+                    .unwrap_or(false)
+            })
+            .copied()
+            .collect();
+        let choices = if best.is_empty() { neighbors } else { best };
+        if let Some(pick) = choices.choose(rng) {
+            hgrid.link_cells(coords, *pick);
+        }
+    }
+
+    hgrid
+}
+
+pub const ALGORITHMS: [&str; 5] = [
+    "binary-tree",
+    "sidewinder",
+    "aldous-broder",
+    "wilsons",
+    "kruskal",
+];
+
+pub fn algorithm_name(algorithm: &str) -> &str {
+    match algorithm {
+        "binary-tree" => "Binary Tree",
+        "sidewinder" => "Sidewinder",
+        "aldous-broder" => "Aldous Broder",
+        "wilsons" => "Wilson's",
+        "kruskal" => "Kruskal's",
+        _ => algorithm,
+    }
+}
+
+pub fn generate(algorithm: &str, rows: i32, columns: i32) -> Result<hash_grid::HashGrid, String> {
+    generate_with_rng(algorithm, rows, columns, &mut thread_rng())
+}
+
+pub fn generate_with_rng<R: Rng + ?Sized>(
+    algorithm: &str,
+    rows: i32,
+    columns: i32,
+    rng: &mut R,
+) -> Result<hash_grid::HashGrid, String> {
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    carve_with_rng(&mut hgrid, algorithm, rng)?;
+    Ok(hgrid)
+}
+
+// Carves a maze into a fresh grid, so callers can switch on link recording first
+pub fn carve(hgrid: &mut hash_grid::HashGrid, algorithm: &str) -> Result<(), String> {
+    carve_with_rng(hgrid, algorithm, &mut thread_rng())
+}
+
+pub fn carve_with_rng<R: Rng + ?Sized>(
+    hgrid: &mut hash_grid::HashGrid,
+    algorithm: &str,
+    rng: &mut R,
+) -> Result<(), String> {
+    let blank = grid::Grid::new(hgrid.rows, hgrid.columns);
+    match algorithm {
+        "binary-tree" => hgrid.link_from_grid(&binary_tree_with_rng(&blank, rng)),
+        "sidewinder" => hgrid.link_from_grid(&sidewinder_with_rng(&blank, rng)),
+        "aldous-broder" => {
+            aldous_broder_with_rng(hgrid, rng);
+        }
+        // the general spanning tree generators, run over the grid's neighbours
+        "wilsons" | "kruskal" => {
+            let tree = spanning::spanning_tree_with_rng(
+                &graph::neighbor_graph(hgrid),
+                algorithm.parse()?,
+                rng,
+            )?;
+            graph::link_edges(&tree, hgrid);
+        }
+        _ => return Err(format!("Unimplemented algorithm {}", algorithm)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{aldous_broder, braid, generate_with_rng};
+    use crate::hash_grid::HashGrid;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::{HashMap, HashSet};
+
+    type Edge = ((i32, i32), (i32, i32));
+
+    fn grid_edges(rows: i32, columns: i32) -> Vec<Edge> {
+        let mut edges = vec![];
+        for row in 0..rows {
+            for col in 0..columns {
+                if col + 1 < columns {
+                    edges.push(((row, col), (row, col + 1)));
+                }
+                if row + 1 < rows {
+                    edges.push(((row, col), (row + 1, col)));
+                }
+            }
+        }
+        edges
+    }
+
+    fn find(parent: &mut [usize], idx: usize) -> usize {
+        let mut root = idx;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[idx] = root;
+        root
+    }
+
+    // Brute force: every subset of n - 1 edges which doesn't close a loop is a spanning tree
+    fn spanning_trees(rows: i32, columns: i32) -> HashSet<Vec<Edge>> {
+        let edges = grid_edges(rows, columns);
+        let size = (rows * columns) as usize;
+        let mut trees = HashSet::new();
+        for mask in 0u32..(1 << edges.len()) {
+            if mask.count_ones() as usize != size - 1 {
+                continue;
+            }
+            let mut parent: Vec<usize> = (0..size).collect();
+            let mut tree = vec![];
+            let mut acyclic = true;
+            for (bit, edge) in edges.iter().enumerate() {
+                if mask & (1 << bit) == 0 {
+                    continue;
+                }
+                let first = find(&mut parent, (edge.0 .0 * columns + edge.0 .1) as usize);
+                let second = find(&mut parent, (edge.1 .0 * columns + edge.1 .1) as usize);
+                if first == second {
+                    acyclic = false;
+                    break;
+                }
+                parent[first] = second;
+                tree.push(*edge);
+            }
+            if acyclic {
+                tree.sort_unstable();
+                trees.insert(tree);
+            }
+        }
+        trees
+    }
+
+    fn maze_edges(hgrid: &HashGrid) -> Vec<Edge> {
+        let mut edges = vec![];
+        for row in 0..hgrid.rows {
+            for col in 0..hgrid.columns {
+                for key in hgrid.get_item((row, col)).unwrap().links.iter() {
+                    if (row, col) < *key {
+                        edges.push(((row, col), *key));
+                    }
+                }
+            }
+        }
+        edges.sort_unstable();
+        edges
+    }
+
+    // Wilson-Hilferty approximation of the chi-squared quantile at p = 0.001
+    fn chi_squared_critical(df: f64) -> f64 {
+        let z = 3.09;
+        let term = 2.0 / (9.0 * df);
+        df * (1.0 - term + z * term.sqrt()).powi(3)
+    }
+
+    // Returns the chi-squared statistic and its critical value against the uniform distribution
+    fn uniformity(algorithm: &str, rows: i32, columns: i32, seed: u64) -> (f64, f64) {
+        let trees = spanning_trees(rows, columns);
+        let samples = 50 * trees.len();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut counts: HashMap<Vec<Edge>, u32> = HashMap::new();
+        for _ in 0..samples {
+            let hgrid = generate_with_rng(algorithm, rows, columns, &mut rng).unwrap();
+            let edges = maze_edges(&hgrid);
+            assert!(trees.contains(&edges), "{} made a non-tree", algorithm);
+            *counts.entry(edges).or_insert(0) += 1;
+        }
+        let expected = samples as f64 / trees.len() as f64;
+        let statistic: f64 = trees
+            .iter()
+            .map(|tree| {
+                let observed = *counts.get(tree).unwrap_or(&0) as f64;
+                (observed - expected) * (observed - expected) / expected
+            })
+            .sum();
+        (statistic, chi_squared_critical((trees.len() - 1) as f64))
+    }
+
+    #[test]
+    fn test_spanning_tree_counts() {
+        assert_eq!(spanning_trees(2, 2).len(), 4);
+        assert_eq!(spanning_trees(2, 3).len(), 15);
+        assert_eq!(spanning_trees(3, 3).len(), 192);
+    }
+
+    #[test]
+    fn test_uniform_algorithms() {
+        for algorithm in ["aldous-broder", "wilsons"] {
+            for (rows, columns, seed) in [(2, 2, 11), (2, 3, 23), (3, 3, 33)] {
+                let (statistic, critical) = uniformity(algorithm, rows, columns, seed);
+                assert!(
+                    statistic < critical,
+                    "{} {}x{}: chi-squared {} >= {}",
+                    algorithm,
+                    rows,
+                    columns,
+                    statistic,
+                    critical
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_biased_algorithms_not_uniform() {
+        for algorithm in ["binary-tree", "sidewinder"] {
+            let (statistic, critical) = uniformity(algorithm, 3, 3, 7);
+            assert!(statistic > critical, "{} looks uniform", algorithm);
+        }
+    }
+
+    #[test]
+    fn test_braid_removes_all_deadends() {
+        let mut hgrid = HashGrid::new(6, 6);
+        let hgrid = aldous_broder(&mut hgrid);
+        assert!(!hgrid.deadends().is_empty());
+        let hgrid = braid(hgrid, 1.0);
+        assert!(hgrid.deadends().is_empty());
+    }
+
+    #[test]
+    fn test_braid_zero_keeps_perfect_maze() {
+        let mut hgrid = HashGrid::new(6, 6);
+        let hgrid = aldous_broder(&mut hgrid);
+        let before = hgrid.deadends();
+        let hgrid = braid(hgrid, 0.0);
+        assert_eq!(hgrid.deadends(), before);
+    }
+}
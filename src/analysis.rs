@@ -0,0 +1,275 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::cell;
+use crate::distances;
+use crate::hash_grid;
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct DirectionCounts {
+    pub north: usize,
+    pub east: usize,
+    pub south: usize,
+    pub west: usize,
+}
+
+impl DirectionCounts {
+    pub fn add(&mut self, way: cell::Direction) {
+        match way {
+            cell::Direction::North => self.north += 1,
+            cell::Direction::East => self.east += 1,
+            cell::Direction::South => self.south += 1,
+            cell::Direction::West => self.west += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.north + self.east + self.south + self.west
+    }
+}
+
+// Each passage is counted once, from the cell at its south or west end, so it runs north or east
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct PassageCounts {
+    pub north: usize,
+    pub east: usize,
+}
+
+impl PassageCounts {
+    pub fn total(&self) -> usize {
+        self.north + self.east
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct MazeStats {
+    pub cells: usize,
+    pub deadends: usize,
+    pub deadend_percent: f64,
+    pub junctions: usize,
+    pub corridors: usize,
+    pub turns: usize,
+    pub diameter: u32,
+    pub average_path_length: f64,
+    pub river_factor: f64,
+    pub link_directions: PassageCounts,
+    pub deadend_directions: DirectionCounts,
+}
+
+fn is_straight(cll: &cell::Cell) -> bool {
+    (cll.direction_has_link(cell::Direction::North)
+        && cll.direction_has_link(cell::Direction::South))
+        || (cll.direction_has_link(cell::Direction::East)
+            && cll.direction_has_link(cell::Direction::West))
+}
+
+// Corridors are runs of cells with exactly two links: the river factor is their mean length
+fn river_factor(hgrid: &hash_grid::HashGrid) -> f64 {
+    let mut seen: HashSet<(i32, i32)> = HashSet::new();
+    let mut runs: Vec<usize> = vec![];
+    for rownum in 0..hgrid.rows {
+        for colnum in 0..hgrid.columns {
+            let start = hgrid.get_item((rownum, colnum)).unwrap();
+            if start.links.len() != 2 || seen.contains(&start.coords()) {
+                continue;
+            }
+            let mut run = 0;
+            let mut frontier = vec![start];
+            seen.insert(start.coords());
+            while let Some(cll) = frontier.pop() {
+                run += 1;
+                for key in cll.links.iter() {
+                    if let Some(next) = hgrid.get_item(*key) {
+                        if next.links.len() == 2 && !seen.contains(key) {
+                            seen.insert(*key);
+                            frontier.push(next);
+                        }
+                    }
+                }
+            }
+            runs.push(run);
+        }
+    }
+    if runs.is_empty() {
+        0.0
+    } else {
+        runs.iter().sum::<usize>() as f64 / runs.len() as f64
+    }
+}
+
+// This runs a breadth-first search from every cell, so it is quadratic in the size of the grid
+pub fn analyze(hgrid: &hash_grid::HashGrid) -> MazeStats {
+    let cells = hgrid.len();
+    let mut deadends = 0;
+    let mut junctions = 0;
+    let mut corridors = 0;
+    let mut turns = 0;
+    let mut link_directions = PassageCounts::default();
+    let mut deadend_directions = DirectionCounts::default();
+    let mut diameter = 0u32;
+    let mut path_total = 0u64;
+    let mut path_count = 0u64;
+
+    for rownum in 0..hgrid.rows {
+        for colnum in 0..hgrid.columns {
+            let cll = hgrid.get_item((rownum, colnum)).unwrap();
+            match cll.links.len() {
+                1 => deadends += 1,
+                2 if is_straight(cll) => corridors += 1,
+                2 => turns += 1,
+                n if n > 2 => junctions += 1,
+                _ => (),
+            }
+            for key in cll.links.iter() {
+                match cll.neighbor_direction(*key) {
+                    Some(cell::Direction::North) => link_directions.north += 1,
+                    Some(cell::Direction::East) => link_directions.east += 1,
+                    _ => (),
+                }
+                if cll.links.len() == 1 {
+                    if let Some(way) = cll.neighbor_direction(*key) {
+                        deadend_directions.add(way);
+                    }
+                }
+            }
+
+            let dm = distances::DistanceMap::from_hashgrid((rownum, colnum), hgrid);
+            for dist in dm.map.values() {
+                diameter = diameter.max(*dist);
+                if *dist > 0 {
+                    path_total += *dist as u64;
+                    path_count += 1;
+                }
+            }
+        }
+    }
+
+    MazeStats {
+        cells,
+        deadends,
+        deadend_percent: if cells == 0 {
+            0.0
+        } else {
+            100.0 * deadends as f64 / cells as f64
+        },
+        junctions,
+        corridors,
+        turns,
+        diameter,
+        average_path_length: if path_count == 0 {
+            0.0
+        } else {
+            path_total as f64 / path_count as f64
+        },
+        river_factor: river_factor(hgrid),
+        link_directions,
+        deadend_directions,
+    }
+}
+
+impl std::fmt::Display for DirectionCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let total = self.total().max(1) as f64;
+        write!(
+            f,
+            "N {:.1}%  E {:.1}%  S {:.1}%  W {:.1}%",
+            100.0 * self.north as f64 / total,
+            100.0 * self.east as f64 / total,
+            100.0 * self.south as f64 / total,
+            100.0 * self.west as f64 / total,
+        )
+    }
+}
+
+impl std::fmt::Display for PassageCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let total = self.total().max(1) as f64;
+        write!(
+            f,
+            "N {:.1}%  E {:.1}%",
+            100.0 * self.north as f64 / total,
+            100.0 * self.east as f64 / total,
+        )
+    }
+}
+
+impl std::fmt::Display for MazeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Cells:               {}", self.cells)?;
+        writeln!(
+            f,
+            "Dead ends:           {} ({:.1}%)",
+            self.deadends, self.deadend_percent
+        )?;
+        writeln!(f, "Junctions:           {}", self.junctions)?;
+        writeln!(f, "Corridors:           {}", self.corridors)?;
+        writeln!(f, "Turns:               {}", self.turns)?;
+        writeln!(f, "Diameter:            {}", self.diameter)?;
+        writeln!(f, "Average path length: {:.2}", self.average_path_length)?;
+        writeln!(f, "River factor:        {:.2}", self.river_factor)?;
+        writeln!(f, "Link directions:     {}", self.link_directions)?;
+        writeln!(f, "Dead-end exits:      {}", self.deadend_directions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::analyze;
+    use crate::algorithms;
+    use crate::hash_grid::HashGrid;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_analyze_corridor() {
+        // a single row linked end to end is one long corridor
+        let mut hgrid = HashGrid::new(1, 4);
+        hgrid.link_cells((0, 0), (0, 1));
+        hgrid.link_cells((0, 1), (0, 2));
+        hgrid.link_cells((0, 2), (0, 3));
+        let stats = analyze(&hgrid);
+        assert_eq!(stats.deadends, 2);
+        assert_eq!(stats.corridors, 2);
+        assert_eq!(stats.turns, 0);
+        assert_eq!(stats.junctions, 0);
+        assert_eq!(stats.diameter, 3);
+        assert_eq!(stats.river_factor, 2.0);
+        // pairs at distance 1, 2, 3 occur 3, 2 and 1 times in each direction
+        assert_eq!(stats.average_path_length, 10.0 / 6.0);
+        assert_eq!(stats.link_directions.east, 3);
+        assert_eq!(stats.link_directions.north, 0);
+        assert_eq!(stats.deadend_directions.east, 1);
+        assert_eq!(stats.deadend_directions.west, 1);
+    }
+
+    #[test]
+    fn test_analyze_turns() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        hgrid.link_cells((0, 0), (1, 0));
+        hgrid.link_cells((0, 1), (1, 1));
+        let stats = analyze(&hgrid);
+        assert_eq!(stats.deadends, 2);
+        assert_eq!(stats.turns, 2);
+        assert_eq!(stats.deadend_percent, 50.0);
+    }
+
+    #[test]
+    fn test_binary_tree_bias() {
+        let mut hgrid = HashGrid::new(8, 8);
+        let mut rng = StdRng::seed_from_u64(11);
+        algorithms::carve_with_rng(&mut hgrid, "binary-tree", &mut rng).unwrap();
+        let stats = analyze(&hgrid);
+        assert_eq!(stats.link_directions.total(), 63);
+        // every cell but the north east corner carves north or east, so only the corner can
+        // be a dead end leading south or west
+        let exits = &stats.deadend_directions;
+        assert!(exits.north + exits.east >= stats.deadends - 1);
+        assert!(exits.south + exits.west <= 1);
+
+        let mut hgrid = HashGrid::new(8, 8);
+        algorithms::carve_with_rng(&mut hgrid, "aldous-broder", &mut rng).unwrap();
+        let exits = analyze(&hgrid).deadend_directions;
+        assert!(exits.south + exits.west > 1);
+    }
+}
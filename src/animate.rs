@@ -0,0 +1,339 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::distances;
+use crate::hash_grid;
+use crate::render;
+use crate::render::Renderer;
+use crate::search;
+
+pub struct AnimationOptions {
+    // how many links are carved, or search steps taken, between frames
+    pub frame_skip: usize,
+    // in hundredths of a second, as GIF stores it
+    pub delay: u16,
+    // the finished maze is held on screen this much longer than the other frames
+    pub final_delay: u16,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            frame_skip: 1,
+            delay: 5,
+            final_delay: 200,
+        }
+    }
+}
+
+// raqote stores premultiplied ARGB, which we flatten onto white since GIF has no partial alpha
+fn to_rgba(dt: &raqote::DrawTarget) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(dt.get_data().len() * 4);
+    for pixel in dt.get_data() {
+        let alpha = (pixel >> 24) as u8;
+        for shift in [16, 8, 0] {
+            let channel = (pixel >> shift) as u8;
+            rgba.push(channel.saturating_add(255 - alpha));
+        }
+        rgba.push(255);
+    }
+    rgba
+}
+
+// Frames go straight to the encoder. APNG needs the frame count in its header before any frames,
+// so it's worked out up front.
+enum Output {
+    Gif(gif::Encoder<File>),
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+// Writes rendered frames, which all share the size set by the first `begin`
+struct Animation {
+    width: i32,
+    height: i32,
+    output: Output,
+}
+
+impl Animation {
+    // a .png name gets an APNG of exactly `frames` frames, anything else a GIF
+    fn new(
+        renderer: &mut render::PngRenderer,
+        rows: i32,
+        columns: i32,
+        frames: u32,
+        filename: &str,
+    ) -> Result<Self, String> {
+        renderer.begin(rows, columns);
+        let width = renderer.draw_target().width();
+        let height = renderer.draw_target().height();
+        if width > u16::MAX as i32 || height > u16::MAX as i32 {
+            return Err(format!("{}x{} is too large to animate", width, height));
+        }
+        let is_png = match std::path::Path::new(filename).extension() {
+            Some(ext) => ext.eq_ignore_ascii_case("png"),
+            None => false,
+        };
+        let file = File::create(filename).map_err(|err| format!("Failed creating file {}", err))?;
+        let output = if is_png {
+            let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            // zero plays loops forever
+            encoder
+                .set_animated(frames, 0)
+                .map_err(|err| format!("Failed writing png {}", err))?;
+            let writer = encoder
+                .write_header()
+                .map_err(|err| format!("Failed writing png {}", err))?;
+            Output::Apng(writer)
+        } else {
+            let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])
+                .map_err(|err| format!("Failed writing gif {}", err))?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(|err| format!("Failed writing gif {}", err))?;
+            Output::Gif(encoder)
+        };
+        Ok(Self {
+            width,
+            height,
+            output,
+        })
+    }
+
+    fn push(&mut self, renderer: &mut render::PngRenderer, delay: u16) -> Result<(), String> {
+        let mut rgba = to_rgba(renderer.draw_target());
+        match &mut self.output {
+            Output::Gif(encoder) => {
+                let mut gif_frame = gif::Frame::from_rgba_speed(
+                    self.width as u16,
+                    self.height as u16,
+                    &mut rgba,
+                    10,
+                );
+                gif_frame.delay = delay;
+                encoder
+                    .write_frame(&gif_frame)
+                    .map_err(|err| format!("Failed writing gif {}", err))
+            }
+            Output::Apng(writer) => writer
+                .set_frame_delay(delay, 100)
+                .and_then(|_| writer.write_image_data(&rgba))
+                .map_err(|err| format!("Failed writing png {}", err)),
+        }
+    }
+
+    // the GIF encoder writes its trailer when it's dropped
+    fn finish(self) -> Result<(), String> {
+        match self.output {
+            Output::Gif(_) => Ok(()),
+            Output::Apng(writer) => writer
+                .finish()
+                .map_err(|err| format!("Failed writing png {}", err)),
+        }
+    }
+}
+
+// Replays the recorded links onto a blank grid, adding a frame every `frame_skip` links.
+// The cell most recently carved into is marked so the walk is easy to follow.
+pub fn animate_generation(
+    rows: i32,
+    columns: i32,
+    history: &[hash_grid::Link],
+    renderer: &mut render::PngRenderer,
+    options: &AnimationOptions,
+    filename: &str,
+) -> Result<(), String> {
+    let frame_skip = options.frame_skip.max(1);
+    // the blank grid, one every `frame_skip` links before the last, then the finished maze
+    let frames = 2 + history.len().saturating_sub(1) / frame_skip;
+    let mut animation = Animation::new(renderer, rows, columns, frames as u32, filename)?;
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    let mut add_frame = |hgrid: &hash_grid::HashGrid, latest: Option<(i32, i32)>, delay: u16| {
+        hgrid.render(renderer, render::Palette::Green);
+        if let Some(coords) = latest {
+            renderer.draw_marker(coords, render::Marker::Goal);
+        }
+        animation.push(renderer, delay)
+    };
+
+    add_frame(&hgrid, None, options.delay)?;
+    for (idx, (first, second)) in history.iter().enumerate() {
+        hgrid.link_cells(*first, *second);
+        if (idx + 1) % frame_skip == 0 && idx + 1 < history.len() {
+            add_frame(&hgrid, Some(*second), options.delay)?;
+        }
+    }
+    add_frame(&hgrid, None, options.final_delay)?;
+    animation.finish()
+}
+
+// Colours for cells the solver has expanded and cells waiting to be expanded
+pub const CLOSED_COLOUR: (u8, u8, u8) = (0xbb, 0xd5, 0xee);
+pub const OPEN_COLOUR: (u8, u8, u8) = (0xff, 0xaa, 0x33);
+
+// One frame per search step, with the path from start to goal drawn over the last one
+pub fn animate_search(
+    hgrid: &hash_grid::HashGrid,
+    solver: search::Solver,
+    start: (i32, i32),
+    goal: (i32, i32),
+    renderer: &mut render::PngRenderer,
+    options: &AnimationOptions,
+    filename: &str,
+) -> Result<(), String> {
+    let cells = hgrid.cells();
+    let steps = search::search_steps(solver, hgrid, start, goal);
+    let frame_skip = options.frame_skip.max(1);
+    // every `frame_skip` steps before the last, then the last with the path
+    let frames = steps.len().saturating_sub(1).div_ceil(frame_skip) + 1;
+    let mut animation =
+        Animation::new(renderer, hgrid.rows, hgrid.columns, frames as u32, filename)?;
+    let mut closed: Vec<(i32, i32)> = vec![];
+    let draw_step =
+        |renderer: &mut render::PngRenderer, closed: &[(i32, i32)], step: &search::SearchStep| {
+            renderer.begin(hgrid.rows, hgrid.columns);
+            for (coords, colour) in closed
+                .iter()
+                .map(|coords| (coords, CLOSED_COLOUR))
+                .chain(step.open.iter().map(|coords| (coords, OPEN_COLOUR)))
+            {
+                renderer.fill_cell(hgrid.get_item(*coords).unwrap(), 0, colour);
+            }
+            render::draw_walls(renderer, &cells);
+        };
+
+    for (idx, step) in steps.iter().enumerate() {
+        closed.extend_from_slice(&step.newly_closed);
+        if idx % frame_skip == 0 && idx + 1 < steps.len() {
+            draw_step(renderer, &closed, step);
+            animation.push(renderer, options.delay)?;
+        }
+    }
+    if let Some(step) = steps.last() {
+        draw_step(renderer, &closed, step);
+        let dm = distances::DistanceMap::from_hashgrid(start, hgrid);
+        if dm.map.contains_key(&goal) {
+            render::draw_route(renderer, &dm.path_cells(goal, hgrid));
+        }
+        animation.push(renderer, options.final_delay)?;
+    }
+    animation.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{animate_generation, animate_search, AnimationOptions};
+    use crate::algorithms;
+    use crate::hash_grid::HashGrid;
+    use crate::render;
+    use crate::search::Solver;
+
+    #[test]
+    fn test_history_rebuilds_maze() {
+        for algorithm in algorithms::ALGORITHMS.iter() {
+            let mut hgrid = HashGrid::new(5, 6);
+            hgrid.record_links();
+            algorithms::carve(&mut hgrid, algorithm).unwrap();
+            let history = hgrid.history().unwrap();
+            // a perfect maze is carved with exactly one link per cell after the first
+            assert_eq!(history.len(), 29, "{}", algorithm);
+
+            let mut replayed = HashGrid::new(5, 6);
+            for (first, second) in history {
+                replayed.link_cells(*first, *second);
+            }
+            assert_eq!(format!("{}", replayed), format!("{}", hgrid));
+        }
+    }
+
+    #[test]
+    fn test_frame_count() {
+        let mut hgrid = HashGrid::new(3, 3);
+        hgrid.record_links();
+        algorithms::carve(&mut hgrid, "aldous-broder").unwrap();
+        let mut renderer = render::PngRenderer::new(
+            10,
+            0.,
+            render::WallStyle::default(),
+            render::PathStyle::default(),
+        );
+        let fname = std::env::temp_dir().join("mazes-generation-test.gif");
+        let options = AnimationOptions {
+            frame_skip: 3,
+            ..AnimationOptions::default()
+        };
+        animate_generation(
+            3,
+            3,
+            hgrid.history().unwrap(),
+            &mut renderer,
+            &options,
+            fname.to_str().unwrap(),
+        )
+        .unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(std::fs::File::open(&fname).unwrap())
+            .unwrap();
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        // the blank grid, after links 3 and 6, then the finished maze
+        assert_eq!(frames, 4);
+        std::fs::remove_file(fname).unwrap();
+
+        // the APNG header is written before any frames, so its count has to match up front
+        let fname = std::env::temp_dir().join("mazes-generation-test.png");
+        animate_generation(
+            3,
+            3,
+            hgrid.history().unwrap(),
+            &mut renderer,
+            &options,
+            fname.to_str().unwrap(),
+        )
+        .unwrap();
+        let decoder = png::Decoder::new(std::fs::File::open(&fname).unwrap());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 4);
+        std::fs::remove_file(fname).unwrap();
+    }
+
+    #[test]
+    fn test_search_apng() {
+        let hgrid = algorithms::generate("sidewinder", 4, 4).unwrap();
+        let mut renderer = render::PngRenderer::new(
+            10,
+            0.,
+            render::WallStyle::default(),
+            render::PathStyle::default(),
+        );
+        let fname = std::env::temp_dir().join("mazes-search-test.png");
+        animate_search(
+            &hgrid,
+            Solver::Bfs,
+            (0, 0),
+            (3, 3),
+            &mut renderer,
+            &AnimationOptions::default(),
+            fname.to_str().unwrap(),
+        )
+        .unwrap();
+
+        let decoder = png::Decoder::new(std::fs::File::open(&fname).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let steps = crate::search::bfs_steps(&hgrid, (0, 0), (3, 3)).len() as u32;
+        let actl = *reader.info().animation_control().unwrap();
+        assert_eq!(actl.num_frames, steps);
+        assert_eq!(actl.num_plays, 0);
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let mut frames = 0;
+        while reader.next_frame(&mut buf).is_ok() {
+            frames += 1;
+        }
+        assert_eq!(frames, steps);
+        std::fs::remove_file(fname).unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::cell;
+use crate::hash_grid;
+use crate::render;
+
+// Characters across the inside of a cell, between the corners on either side of it
+const CELL_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AsciiStyle {
+    // +----+ and | like the Display impl for HashGrid
+    Classic,
+    // box-drawing characters with the right junction at each corner
+    Unicode,
+    // every wall and corner is a pixel, packed two to a character with half blocks
+    Blocks,
+}
+
+impl std::str::FromStr for AsciiStyle {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "classic" => Ok(AsciiStyle::Classic),
+            "unicode" => Ok(AsciiStyle::Unicode),
+            "blocks" => Ok(AsciiStyle::Blocks),
+            _ => Err(format!("Unknown ascii style {}", name)),
+        }
+    }
+}
+
+// Picks the box-drawing glyph for a corner from the walls which meet there
+fn junction(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (false, false, false, false) => ' ',
+        (true, false, false, false) => '╵',
+        (false, true, false, false) => '╷',
+        (false, false, true, false) => '╴',
+        (false, false, false, true) => '╶',
+        (true, true, false, false) => '│',
+        (false, false, true, true) => '─',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    }
+}
+
+pub struct AsciiRenderer {
+    style: AsciiStyle,
+    rows: i32,
+    columns: i32,
+    // (grid line, column) for walls along the top of a cell
+    horizontal: HashSet<(i32, i32)>,
+    // (row, grid line) for walls along the left of a cell
+    vertical: HashSet<(i32, i32)>,
+    bodies: HashMap<(i32, i32), String>,
+}
+
+impl AsciiRenderer {
+    pub fn new(style: AsciiStyle) -> Self {
+        Self {
+            style,
+            rows: 0,
+            columns: 0,
+            horizontal: HashSet::new(),
+            vertical: HashSet::new(),
+            bodies: HashMap::new(),
+        }
+    }
+
+    fn corner(&self, line: i32, column: i32) -> (bool, bool, bool, bool) {
+        (
+            self.vertical.contains(&(line - 1, column)),
+            self.vertical.contains(&(line, column)),
+            self.horizontal.contains(&(line, column - 1)),
+            self.horizontal.contains(&(line, column)),
+        )
+    }
+
+    fn body(&self, coords: (i32, i32)) -> String {
+        self.bodies
+            .get(&coords)
+            .cloned()
+            .unwrap_or_else(|| " ".repeat(CELL_WIDTH))
+    }
+
+    fn text_lines(&self) -> String {
+        let unicode = self.style == AsciiStyle::Unicode;
+        let (wall, side) = if unicode {
+            ("─".repeat(CELL_WIDTH), '│')
+        } else {
+            ("-".repeat(CELL_WIDTH), '|')
+        };
+        let corner = |line: i32, column: i32| {
+            if unicode {
+                let (up, down, left, right) = self.corner(line, column);
+                junction(up, down, left, right)
+            } else {
+                '+'
+            }
+        };
+        let mut text = String::new();
+        for line in 0..=self.rows {
+            for column in 0..self.columns {
+                text.push(corner(line, column));
+                if self.horizontal.contains(&(line, column)) {
+                    text.push_str(&wall);
+                } else {
+                    text.push_str(&" ".repeat(CELL_WIDTH));
+                }
+            }
+            text.push(corner(line, self.columns));
+            text.push('\n');
+            if line == self.rows {
+                break;
+            }
+            for column in 0..=self.columns {
+                if self.vertical.contains(&(line, column)) {
+                    text.push(side);
+                } else {
+                    text.push(' ');
+                }
+                if column < self.columns {
+                    text.push_str(&self.body((line, column)));
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn text_blocks(&self) -> String {
+        let height = self.rows * 2 + 1;
+        let width = self.columns * 2 + 1;
+        let pixel = |py: i32, px: i32| -> bool {
+            if py >= height {
+                return false;
+            }
+            match (py % 2 == 0, px % 2 == 0) {
+                (true, true) => {
+                    let (up, down, left, right) = self.corner(py / 2, px / 2);
+                    up || down || left || right
+                }
+                (true, false) => self.horizontal.contains(&(py / 2, px / 2)),
+                (false, true) => self.vertical.contains(&(py / 2, px / 2)),
+                (false, false) => false,
+            }
+        };
+        let mut text = String::new();
+        for py in (0..height).step_by(2) {
+            for px in 0..width {
+                text.push(match (pixel(py, px), pixel(py + 1, px)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn text(&self) -> String {
+        match self.style {
+            AsciiStyle::Blocks => self.text_blocks(),
+            _ => self.text_lines(),
+        }
+    }
+}
+
+impl render::Renderer for AsciiRenderer {
+    fn begin(&mut self, rows: i32, columns: i32) {
+        self.rows = rows;
+        self.columns = columns;
+        self.horizontal.clear();
+        self.vertical.clear();
+        self.bodies.clear();
+    }
+
+    fn fill_cell(&mut self, cll: &cell::Cell, distance: u32, _colour: (u8, u8, u8)) {
+        let body = format!(
+            " {number:>0width$} ",
+            number = distance,
+            width = CELL_WIDTH - 2
+        );
+        self.bodies.insert(cll.coords(), body);
+    }
+
+    // with a cell size of one, wall coordinates are grid lines offset by the margin
+    fn draw_wall(&mut self, wall: &render::Wall) {
+        let x = wall.x1.min(wall.x2) as i32 - 1;
+        let y = wall.y1.min(wall.y2) as i32 - 1;
+        if wall.y1 == wall.y2 {
+            self.horizontal.insert((y, x));
+        } else {
+            self.vertical.insert((y, x));
+        }
+    }
+
+    // the distances already mark out the path
+    fn draw_path(&mut self, _path: &[(i32, i32)]) {}
+
+    fn draw_marker(&mut self, coords: (i32, i32), marker: render::Marker) {
+        let label = match marker {
+            render::Marker::Start => 'S',
+            render::Marker::Goal => 'G',
+        };
+        let mut body: Vec<char> = self.body(coords).chars().collect();
+        body[0] = label;
+        self.bodies.insert(coords, body.into_iter().collect());
+    }
+
+    fn finish(&mut self, filename: &str) -> Result<(), String> {
+        fs::write(filename, self.text()).map_err(|err| format!("Failed writing file {}", err))
+    }
+
+    fn cell_size(&self) -> i32 {
+        1
+    }
+}
+
+// Reads back the +---+ mazes printed by Grid and HashGrid, with cells of any width. Lines before
+// the top wall are skipped, as is anything written inside the cells.
+pub fn parse(text: &str) -> Result<hash_grid::HashGrid, String> {
+    let lines: Vec<Vec<char>> = text
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with('+'))
+        .map(|line| line.trim_end().chars().collect())
+        .take_while(|line: &Vec<char>| !line.is_empty())
+        .collect();
+    if lines.len() < 3 {
+        return Err("No maze found".to_string());
+    }
+    let indent = lines[0].iter().take_while(|ch| **ch == ' ').count();
+    let top: &[char] = &lines[0][indent..];
+    let width = top[1..]
+        .iter()
+        .position(|ch| *ch == '+')
+        .ok_or_else(|| "The top wall should have a + at every corner".to_string())?;
+    if width == 0 || (top.len() - 1) % (width + 1) != 0 {
+        return Err("The top wall should have a + at every corner".to_string());
+    }
+    if lines.len() % 2 == 0 {
+        return Err("The maze should end with a wall line".to_string());
+    }
+    let columns = ((top.len() - 1) / (width + 1)) as i32;
+    let rows = (lines.len() / 2) as i32;
+
+    // lines are padded so a trimmed line reads as open space
+    let at =
+        |line: usize, pos: usize| -> char { lines[line].get(indent + pos).copied().unwrap_or(' ') };
+    for (idx, line) in lines.iter().enumerate().step_by(2) {
+        for colnum in 0..=columns as usize {
+            if at(idx, colnum * (width + 1)) != '+' {
+                return Err(format!(
+                    "Expected a + at line {} column {}",
+                    idx + 1,
+                    indent + colnum * (width + 1) + 1
+                ));
+            }
+        }
+        if line.len() > indent + top.len() {
+            return Err(format!("Line {} is wider than the top wall", idx + 1));
+        }
+    }
+
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    for rownum in 0..rows {
+        let body = rownum as usize * 2 + 1;
+        let below = body + 1;
+        for colnum in 0..columns {
+            let left = colnum as usize * (width + 1);
+            if colnum + 1 < columns && at(body, left + width + 1) == ' ' {
+                hgrid.link_cells((rownum, colnum), (rownum, colnum + 1));
+            }
+            let open = (1..=width).all(|offset| at(below, left + offset) == ' ');
+            if rownum + 1 < rows && open {
+                hgrid.link_cells((rownum, colnum), (rownum + 1, colnum));
+            }
+        }
+    }
+    Ok(hgrid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, AsciiRenderer, AsciiStyle};
+    use crate::algorithms;
+    use crate::grid::Grid;
+    use crate::hash_grid::HashGrid;
+    use crate::render;
+
+    #[test]
+    fn test_matches_display() {
+        let mut hgrid = algorithms::generate("aldous-broder", 5, 7).unwrap();
+        let mut renderer = AsciiRenderer::new(AsciiStyle::Classic);
+        hgrid.render(&mut renderer, render::Palette::Green);
+        assert_eq!(renderer.text(), format!("{}", hgrid));
+
+        hgrid.build_distance_map();
+        hgrid.render(&mut renderer, render::Palette::Green);
+        assert_eq!(renderer.text(), format!("{}", hgrid));
+    }
+
+    #[test]
+    fn test_unicode_junctions() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        hgrid.link_cells((0, 0), (1, 0));
+        hgrid.link_cells((1, 0), (1, 1));
+        let mut renderer = AsciiRenderer::new(AsciiStyle::Unicode);
+        hgrid.render(&mut renderer, render::Palette::Green);
+        let expected = "┌─────────┐\n│         │\n│    ╶────┤\n│         │\n└─────────┘\n";
+        assert_eq!(renderer.text(), expected);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let mut hgrid = HashGrid::new(1, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        let mut renderer = AsciiRenderer::new(AsciiStyle::Blocks);
+        hgrid.render(&mut renderer, render::Palette::Green);
+        assert_eq!(renderer.text(), "█▀▀▀█\n▀▀▀▀▀\n");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let mut hgrid = algorithms::generate("aldous-broder", 6, 8).unwrap();
+        algorithms::braid(&mut hgrid, 0.5);
+        hgrid.build_breadcrumbs_to_longest();
+        let printed = format!("Aldous Broder\n{}", hgrid);
+        let parsed = parse(&printed).unwrap();
+        // the distances printed in the cells don't get in the way
+        for cll in hgrid.cells() {
+            assert_eq!(parsed.get_item(cll.coords()).unwrap().links, cll.links);
+        }
+
+        // the three-wide cells printed by Grid, which only links one side
+        let raw = algorithms::sidewinder(&Grid::new(5, 4));
+        let parsed = parse(&format!("{}", raw)).unwrap();
+        assert_eq!(
+            format!("{}", parsed),
+            format!("{}", HashGrid::from_grid(&raw))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("no maze here").is_err());
+        assert!(parse("+---+\n|   |\n").is_err());
+        assert!(parse("+---+---+\n|       |\n+---+--+\n").is_err());
+    }
+}
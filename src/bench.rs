@@ -0,0 +1,170 @@
+use rand::{thread_rng, Rng};
+
+use crate::algorithms;
+use crate::analysis;
+
+#[derive(Debug)]
+pub struct BiasSummary {
+    pub algorithm: String,
+    pub samples: u32,
+    pub deadend_percent_mean: f64,
+    pub deadend_percent_stddev: f64,
+    pub diameter_min: u32,
+    pub diameter_mean: f64,
+    pub diameter_max: u32,
+    pub diameter_stddev: f64,
+    pub link_directions: analysis::PassageCounts,
+    pub deadend_directions: analysis::DirectionCounts,
+}
+
+fn mean_stddev(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values
+        .iter()
+        .map(|val| (val - mean) * (val - mean))
+        .sum::<f64>()
+        / values.len() as f64;
+    (mean, variance.sqrt())
+}
+
+pub fn bench_bias(
+    algorithm: &str,
+    samples: u32,
+    rows: i32,
+    columns: i32,
+) -> Result<BiasSummary, String> {
+    bench_bias_with_rng(algorithm, samples, rows, columns, &mut thread_rng())
+}
+
+pub fn bench_bias_with_rng<R: Rng + ?Sized>(
+    algorithm: &str,
+    samples: u32,
+    rows: i32,
+    columns: i32,
+    rng: &mut R,
+) -> Result<BiasSummary, String> {
+    let mut deadend_percents: Vec<f64> = vec![];
+    let mut diameters: Vec<u32> = vec![];
+    let mut link_directions = analysis::PassageCounts::default();
+    let mut deadend_directions = analysis::DirectionCounts::default();
+
+    for _ in 0..samples {
+        let hgrid = algorithms::generate_with_rng(algorithm, rows, columns, rng)?;
+        let stats = analysis::analyze(&hgrid);
+        deadend_percents.push(stats.deadend_percent);
+        diameters.push(stats.diameter);
+        link_directions.north += stats.link_directions.north;
+        link_directions.east += stats.link_directions.east;
+        deadend_directions.north += stats.deadend_directions.north;
+        deadend_directions.east += stats.deadend_directions.east;
+        deadend_directions.south += stats.deadend_directions.south;
+        deadend_directions.west += stats.deadend_directions.west;
+    }
+
+    let (deadend_percent_mean, deadend_percent_stddev) = mean_stddev(&deadend_percents);
+    let diameter_values: Vec<f64> = diameters.iter().map(|val| *val as f64).collect();
+    let (diameter_mean, diameter_stddev) = mean_stddev(&diameter_values);
+    Ok(BiasSummary {
+        algorithm: algorithm.to_string(),
+        samples,
+        deadend_percent_mean,
+        deadend_percent_stddev,
+        diameter_min: diameters.iter().copied().min().unwrap_or(0),
+        diameter_mean,
+        diameter_max: diameters.iter().copied().max().unwrap_or(0),
+        diameter_stddev,
+        link_directions,
+        deadend_directions,
+    })
+}
+
+pub fn format_table(summaries: &[BiasSummary]) -> String {
+    let mut table = format!(
+        "{:<16}{:>16}{:>28}{:>16}{:>34}\n",
+        "Algorithm",
+        "Dead ends %",
+        "Diameter min/mean/max",
+        "Links N/E %",
+        "Dead-end exits N/E/S/W %"
+    );
+    for summary in summaries {
+        let links = &summary.link_directions;
+        let link_total = links.total().max(1) as f64;
+        let exits = &summary.deadend_directions;
+        let exit_total = exits.total().max(1) as f64;
+        table.push_str(&format!(
+            "{:<16}{:>16}{:>28}{:>16}{:>34}\n",
+            algorithms::algorithm_name(&summary.algorithm),
+            format!(
+                "{:.1} ± {:.1}",
+                summary.deadend_percent_mean, summary.deadend_percent_stddev
+            ),
+            format!(
+                "{}/{:.1}/{} ± {:.1}",
+                summary.diameter_min,
+                summary.diameter_mean,
+                summary.diameter_max,
+                summary.diameter_stddev
+            ),
+            format!(
+                "{:.1}/{:.1}",
+                100.0 * links.north as f64 / link_total,
+                100.0 * links.east as f64 / link_total,
+            ),
+            format!(
+                "{:.1}/{:.1}/{:.1}/{:.1}",
+                100.0 * exits.north as f64 / exit_total,
+                100.0 * exits.east as f64 / exit_total,
+                100.0 * exits.south as f64 / exit_total,
+                100.0 * exits.west as f64 / exit_total,
+            ),
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bench_bias_with_rng, format_table};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_bench_bias() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let summaries: Vec<_> = ["binary-tree", "sidewinder", "aldous-broder"]
+            .iter()
+            .map(|algorithm| bench_bias_with_rng(algorithm, 20, 6, 6, &mut rng).unwrap())
+            .collect();
+        for summary in summaries.iter() {
+            assert_eq!(summary.samples, 20);
+            assert_eq!(summary.link_directions.total(), 20 * 35);
+            assert!(summary.diameter_min as f64 <= summary.diameter_mean);
+            assert!(summary.diameter_mean <= summary.diameter_max as f64);
+        }
+        // binary tree dead ends lead north or east, sidewinder's never south, aldous-broder's
+        // anywhere
+        let south_west = |idx: usize| {
+            let exits = &summaries[idx].deadend_directions;
+            (exits.south + exits.west) as f64 / exits.total() as f64
+        };
+        assert!(south_west(0) < south_west(1));
+        assert!(south_west(1) < south_west(2));
+        assert_eq!(summaries[1].deadend_directions.south, 0);
+
+        let table = format_table(&summaries);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("Links N/E %"));
+        assert!(lines[0].contains("Dead-end exits N/E/S/W %"));
+        assert!(lines[1].starts_with("Binary Tree"));
+        assert!(lines[3].starts_with("Aldous Broder"));
+        // every row lines up under the header
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() == lines[0].chars().count()));
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+
+use crate::cell;
+use crate::hash_grid;
+
+// Layout, with integers little-endian:
+//   magic "MAZE", version u8, topology u8, rows u32, columns u32, flags u8, seed u64,
+//   braid probability f64, algorithm name length u8 and its UTF-8 bytes (empty when unknown),
+//   then two bits per cell in row-major order, four cells to a byte starting from the low bits.
+// Of each cell's two bits, the low one is its east wall and the high one its south wall.
+// The north and west walls are the south and east walls of the cells next to it.
+pub const MAGIC: &[u8; 4] = b"MAZE";
+pub const VERSION: u8 = 1;
+// Up to the algorithm name, which is all of the header with an unknown algorithm
+const HEADER_LEN: usize = 32;
+const FLAG_SEED: u8 = 1;
+const FLAG_BRAID: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    Rectangular = 0,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub topology: Topology,
+    pub rows: u32,
+    pub columns: u32,
+    pub seed: Option<u64>,
+    pub braid: Option<f64>,
+    pub algorithm: Option<String>,
+}
+
+impl Header {
+    fn len(&self) -> usize {
+        HEADER_LEN + self.algorithm.as_ref().map_or(0, |name| name.len())
+    }
+}
+
+fn bitmap_len(rows: u32, columns: u32) -> usize {
+    (rows as usize * columns as usize).div_ceil(4)
+}
+
+// Algorithm names longer than 255 bytes are cut short
+pub fn write(
+    hgrid: &hash_grid::HashGrid,
+    seed: Option<u64>,
+    braid: Option<f64>,
+    algorithm: Option<&str>,
+) -> Vec<u8> {
+    let (rows, columns) = (hgrid.rows as u32, hgrid.columns as u32);
+    let name = algorithm.unwrap_or("").as_bytes();
+    let name = &name[..name.len().min(u8::MAX as usize)];
+    let mut bytes = Vec::with_capacity(HEADER_LEN + name.len() + bitmap_len(rows, columns));
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.push(Topology::Rectangular as u8);
+    bytes.extend_from_slice(&rows.to_le_bytes());
+    bytes.extend_from_slice(&columns.to_le_bytes());
+    let mut flags = 0;
+    if seed.is_some() {
+        flags |= FLAG_SEED;
+    }
+    if braid.is_some() {
+        flags |= FLAG_BRAID;
+    }
+    bytes.push(flags);
+    bytes.extend_from_slice(&seed.unwrap_or(0).to_le_bytes());
+    bytes.extend_from_slice(&braid.unwrap_or(0.0).to_le_bytes());
+    bytes.push(name.len() as u8);
+    bytes.extend_from_slice(name);
+
+    let mut bitmap = vec![0u8; bitmap_len(rows, columns)];
+    for (idx, cll) in hgrid.cells().into_iter().enumerate() {
+        let mut walls = 0u8;
+        if !cll.direction_has_link(cell::Direction::East) {
+            walls |= 0b01;
+        }
+        if !cll.direction_has_link(cell::Direction::South) {
+            walls |= 0b10;
+        }
+        bitmap[idx / 4] |= walls << ((idx % 4) * 2);
+    }
+    bytes.extend_from_slice(&bitmap);
+    bytes
+}
+
+pub fn read_header(bytes: &[u8]) -> Result<Header, String> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err("Not a binary maze file".to_string());
+    }
+    if bytes[4] != VERSION {
+        return Err(format!("Unsupported binary maze version {}", bytes[4]));
+    }
+    let topology = match bytes[5] {
+        0 => Topology::Rectangular,
+        other => return Err(format!("Unknown topology {}", other)),
+    };
+    let u32_at = |pos: usize| {
+        u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+    };
+    let mut seed_bytes = [0u8; 8];
+    seed_bytes.copy_from_slice(&bytes[15..23]);
+    let mut braid_bytes = [0u8; 8];
+    braid_bytes.copy_from_slice(&bytes[23..31]);
+    let name_len = bytes[31] as usize;
+    let name = bytes
+        .get(HEADER_LEN..HEADER_LEN + name_len)
+        .ok_or_else(|| "Algorithm name is cut short".to_string())?;
+    let algorithm = match std::str::from_utf8(name) {
+        Ok("") => None,
+        Ok(name) => Some(name.to_string()),
+        Err(err) => return Err(format!("Invalid algorithm name {}", err)),
+    };
+    Ok(Header {
+        version: bytes[4],
+        topology,
+        rows: u32_at(6),
+        columns: u32_at(10),
+        seed: if bytes[14] & FLAG_SEED != 0 {
+            Some(u64::from_le_bytes(seed_bytes))
+        } else {
+            None
+        },
+        braid: if bytes[14] & FLAG_BRAID != 0 {
+            Some(f64::from_le_bytes(braid_bytes))
+        } else {
+            None
+        },
+        algorithm,
+    })
+}
+
+pub fn read(bytes: &[u8]) -> Result<(hash_grid::HashGrid, Header), String> {
+    let header = read_header(bytes)?;
+    if header.rows == 0
+        || header.columns == 0
+        || header.rows > i32::MAX as u32
+        || header.columns > i32::MAX as u32
+    {
+        return Err(format!(
+            "Invalid dimensions {}x{}",
+            header.rows, header.columns
+        ));
+    }
+    let bitmap = &bytes[header.len()..];
+    if bitmap.len() != bitmap_len(header.rows, header.columns) {
+        return Err(format!(
+            "Expected {} bytes of walls for {}x{} but found {}",
+            bitmap_len(header.rows, header.columns),
+            header.rows,
+            header.columns,
+            bitmap.len()
+        ));
+    }
+
+    let (rows, columns) = (header.rows as i32, header.columns as i32);
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    for rownum in 0..rows {
+        for colnum in 0..columns {
+            let idx = (rownum as usize) * (columns as usize) + colnum as usize;
+            let walls = (bitmap[idx / 4] >> ((idx % 4) * 2)) & 0b11;
+            // walls on the outside edge are implied, whatever their bits say
+            if walls & 0b01 == 0 && colnum + 1 < columns {
+                hgrid.link_cells((rownum, colnum), (rownum, colnum + 1));
+            }
+            if walls & 0b10 == 0 && rownum + 1 < rows {
+                hgrid.link_cells((rownum, colnum), (rownum + 1, colnum));
+            }
+        }
+    }
+    Ok((hgrid, header))
+}
+
+pub fn save(
+    hgrid: &hash_grid::HashGrid,
+    seed: Option<u64>,
+    braid: Option<f64>,
+    algorithm: Option<&str>,
+    filename: &str,
+) -> Result<(), String> {
+    fs::write(filename, write(hgrid, seed, braid, algorithm))
+        .map_err(|err| format!("Failed writing file {}", err))
+}
+
+pub fn load(filename: &str) -> Result<(hash_grid::HashGrid, Header), String> {
+    let bytes = fs::read(filename).map_err(|err| format!("Failed reading file {}", err))?;
+    read(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read, read_header, write, Topology, HEADER_LEN};
+    use crate::algorithms;
+
+    #[test]
+    fn test_round_trip() {
+        for algorithm in algorithms::ALGORITHMS.iter() {
+            let mut hgrid = algorithms::generate(algorithm, 7, 9).unwrap();
+            // loops have to survive too
+            algorithms::braid(&mut hgrid, 0.5);
+            let bytes = write(&hgrid, Some(1234), Some(0.5), Some(algorithm));
+            let (loaded, header) = read(&bytes).unwrap();
+            assert_eq!(format!("{}", loaded), format!("{}", hgrid), "{}", algorithm);
+            assert_eq!(header.seed, Some(1234));
+            assert_eq!(header.braid, Some(0.5));
+            assert_eq!(header.algorithm.as_deref(), Some(*algorithm));
+            assert_eq!(header.topology, Topology::Rectangular);
+            assert_eq!((header.rows, header.columns), (7, 9));
+        }
+    }
+
+    #[test]
+    fn test_two_bits_per_cell() {
+        let hgrid = algorithms::generate("sidewinder", 100, 100).unwrap();
+        let bytes = write(&hgrid, None, None, None);
+        assert_eq!(bytes.len(), HEADER_LEN + 2500);
+        let header = read_header(&bytes).unwrap();
+        assert_eq!(header.seed, None);
+        assert_eq!(header.braid, None);
+        assert_eq!(header.algorithm, None);
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let hgrid = algorithms::generate("binary-tree", 3, 3).unwrap();
+        let bytes = write(&hgrid, None, None, Some("binary-tree"));
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read(&bytes[..HEADER_LEN + 4]).is_err());
+        assert!(read(b"{\"version\": 1}").is_err());
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert!(read(&future).is_err());
+    }
+}
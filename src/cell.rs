@@ -1,7 +1,8 @@
 use rand::prelude::IteratorRandom;
+use rand::Rng;
 use std::collections;
 
-#[derive(Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub enum Direction {
     North,
     East,
@@ -136,10 +137,13 @@ impl Cell {
             .collect()
     }
     pub fn random_neighbor(&self) -> Option<&(i32, i32)> {
+        self.random_neighbor_with_rng(&mut rand::thread_rng())
+    }
+    pub fn random_neighbor_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&(i32, i32)> {
         vec![&self.north, &self.east, &self.south, &self.west]
             .iter()
             .filter(|&elem| elem.is_some())
             .map(|&elem| elem.as_ref().expect("This should have been filtered out!"))
-            .choose(&mut rand::thread_rng())
+            .choose(rng)
     }
 }
@@ -14,6 +14,10 @@ impl DistanceMap {
         Self { root, map }
     }
 
+    pub fn root(&self) -> (i32, i32) {
+        self.root
+    }
+
     pub fn from_hashgrid(start: (i32, i32), hgrid: &hash_grid::HashGrid) -> Self {
         let mut distance_map = HashMap::new();
         distance_map.insert(start, 0);
@@ -68,19 +72,54 @@ impl DistanceMap {
         goal: (i32, i32),
         hgrid: &hash_grid::HashGrid,
     ) -> HashMap<(i32, i32), u32> {
-        let mut path = HashMap::new();
-        path.insert(goal, *self.map.get(&goal).unwrap());
+        self.path_cells(goal, hgrid)
+            .into_iter()
+            .map(|coords| (coords, *self.map.get(&coords).unwrap()))
+            .collect()
+    }
+
+    // Walks back downhill from the goal to the root, returning the cells in order from the root
+    pub fn path_cells(&self, goal: (i32, i32), hgrid: &hash_grid::HashGrid) -> Vec<(i32, i32)> {
+        let mut path = vec![goal];
         let mut current = goal;
-        while current != goal {
+        while current != self.root {
             let cell = hgrid.get_item(current).unwrap();
-            for key in cell.links.iter() {
-                let neighbor_dist = *self.map.get(key).unwrap();
-                if neighbor_dist < *self.map.get(&current).unwrap() {
-                    path.insert(*key, neighbor_dist);
+            let current_dist = *self.map.get(&current).unwrap();
+            let next = cell.links.iter().find(|key| {
+                self.map
+                    .get(key)
+                    .map(|neighbor_dist| *neighbor_dist < current_dist)
+                    .unwrap_or(false)
+            });
+            match next {
+                Some(key) => {
                     current = *key;
+                    path.push(current);
                 }
+                None => break,
             }
         }
-        path.to_owned()
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DistanceMap;
+    use crate::hash_grid::HashGrid;
+
+    #[test]
+    fn test_path_cells() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((1, 0), (0, 0));
+        hgrid.link_cells((0, 0), (0, 1));
+        hgrid.link_cells((0, 1), (1, 1));
+        let dm = DistanceMap::from_hashgrid((1, 0), &hgrid);
+        assert_eq!(
+            dm.path_cells((1, 1), &hgrid),
+            vec![(1, 0), (0, 0), (0, 1), (1, 1)]
+        );
+        assert_eq!(dm.path_to((0, 1), &hgrid).len(), 3);
     }
 }
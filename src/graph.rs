@@ -1,11 +1,16 @@
-use crate::cell;
 use crate::grid;
+use crate::hash_grid;
 use crate::render;
+use crate::render::Renderer;
+use petgraph::dot::{Config, Dot};
 use petgraph::graph::{NodeIndex, UnGraph};
-use petgraph::visit::IntoNodeIdentifiers;
+use petgraph::visit::{EdgeRef, IntoNodeReferences, NodeRef};
 use std::collections::HashMap;
 
-pub fn from_grid(some_grid: grid::Grid) -> UnGraph<(i32, i32), ()> {
+// Points between neighbouring cells in the positions handed to Graphviz
+const DOT_SPACING: i32 = 36;
+
+pub fn from_grid(some_grid: &grid::Grid) -> UnGraph<(i32, i32), ()> {
     let size = (some_grid.columns * some_grid.rows) as usize;
     let mut graph = UnGraph::with_capacity(size, size * 4);
     let mut node_point_map: HashMap<NodeIndex, (i32, i32)> = HashMap::new();
@@ -34,6 +39,140 @@ pub fn from_grid(some_grid: grid::Grid) -> UnGraph<(i32, i32), ()> {
     graph
 }
 
+// One node per cell in row-major order, with an edge for each passage.
+// Nodes carry their coordinates, so which cells neighbour each other is kept by the layout.
+pub fn from_hashgrid(hgrid: &hash_grid::HashGrid) -> UnGraph<(i32, i32), ()> {
+    let (mut graph, nodes) = cell_nodes(hgrid);
+    for cll in hgrid.cells() {
+        let mut links: Vec<&(i32, i32)> = cll.links.iter().collect();
+        links.sort_unstable();
+        for link in links {
+            // each passage is listed by both of its cells, so only add it from the first
+            if *link > cll.coords() {
+                graph.add_edge(nodes[&cll.coords()], nodes[link], ());
+            }
+        }
+    }
+    graph
+}
+
+// Every pair of neighbouring cells joined, walls or not, for building new mazes on
+pub fn neighbor_graph(hgrid: &hash_grid::HashGrid) -> UnGraph<(i32, i32), ()> {
+    let (mut graph, nodes) = cell_nodes(hgrid);
+    for cll in hgrid.cells() {
+        let mut neighbors = cll.neighbors();
+        neighbors.sort_unstable();
+        for neighbor in neighbors {
+            if *neighbor > cll.coords() {
+                graph.add_edge(nodes[&cll.coords()], nodes[neighbor], ());
+            }
+        }
+    }
+    graph
+}
+
+type CellNodes = (UnGraph<(i32, i32), ()>, HashMap<(i32, i32), NodeIndex>);
+
+fn cell_nodes(hgrid: &hash_grid::HashGrid) -> CellNodes {
+    let size = hgrid.len();
+    let mut graph = UnGraph::with_capacity(size, size * 2);
+    let mut nodes = HashMap::with_capacity(size);
+    for cll in hgrid.cells() {
+        nodes.insert(cll.coords(), graph.add_node(cll.coords()));
+    }
+    (graph, nodes)
+}
+
+// The grid is sized to fit the furthest node, and edges become links.
+// Edges between cells that aren't neighbours can't be drawn, so they're an error.
+pub fn to_hashgrid(graph: &UnGraph<(i32, i32), ()>) -> Result<hash_grid::HashGrid, String> {
+    let mut rows = 0;
+    let mut columns = 0;
+    for (row, column) in graph.node_weights() {
+        if *row < 0 || *column < 0 {
+            return Err(format!("Cell {:?} is outside the grid", (row, column)));
+        }
+        rows = rows.max(row + 1);
+        columns = columns.max(column + 1);
+    }
+    if graph.node_count() == 0 {
+        return Err("The graph has no cells".to_string());
+    }
+    for edge in graph.edge_references() {
+        let (first, second) = (graph[edge.source()], graph[edge.target()]);
+        if (first.0 - second.0).abs() + (first.1 - second.1).abs() != 1 {
+            return Err(format!(
+                "Cell {:?} links to {:?} which isn't a neighbor",
+                first, second
+            ));
+        }
+    }
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    link_edges(graph, &mut hgrid);
+    Ok(hgrid)
+}
+
+// Links cells onto a grid that's already the right size, without checking they're neighbours
+pub fn link_edges(graph: &UnGraph<(i32, i32), ()>, hgrid: &mut hash_grid::HashGrid) {
+    for edge in graph.edge_references() {
+        hgrid.link_cells(graph[edge.source()], graph[edge.target()]);
+    }
+}
+
+// Each node is labelled with its row and column and pinned where the cell is drawn,
+// so `neato -n` lays the graph out like the maze
+pub fn to_dot(graph: &UnGraph<(i32, i32), ()>) -> String {
+    let node_attributes = |_, node: (NodeIndex, &(i32, i32))| {
+        let (row, column) = *node.weight();
+        format!(
+            "label = \"{},{}\" pos = \"{},{}!\" ",
+            row,
+            column,
+            column * DOT_SPACING,
+            -row * DOT_SPACING
+        )
+    };
+    let dot = Dot::with_attr_getters(
+        graph,
+        &[Config::NodeNoLabel, Config::EdgeNoLabel],
+        &|_, _| String::new(),
+        &node_attributes,
+    );
+    format!("{:?}", dot)
+}
+
+pub fn to_graphml(graph: &UnGraph<(i32, i32), ()>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for key in ["row", "column", "x", "y"] {
+        xml.push_str(&format!(
+            "  <key id=\"{0}\" for=\"node\" attr.name=\"{0}\" attr.type=\"int\"/>\n",
+            key
+        ));
+    }
+    xml.push_str("  <graph id=\"maze\" edgedefault=\"undirected\">\n");
+    for (index, (row, column)) in graph.node_references() {
+        xml.push_str(&format!(
+            "    <node id=\"n{}\"><data key=\"row\">{}</data><data key=\"column\">{}</data>\
+             <data key=\"x\">{}</data><data key=\"y\">{}</data></node>\n",
+            index.index(),
+            row,
+            column,
+            column * DOT_SPACING,
+            row * DOT_SPACING
+        ));
+    }
+    for edge in graph.edge_references() {
+        xml.push_str(&format!(
+            "    <edge source=\"n{}\" target=\"n{}\"/>\n",
+            edge.source().index(),
+            edge.target().index()
+        ));
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
 pub fn base_from_coords(rows: i32, columns: i32) -> UnGraph<(i32, i32), ()> {
     let size = (columns * rows) as usize;
     let mut graph = UnGraph::with_capacity(size, size * 4);
@@ -45,6 +184,18 @@ pub fn base_from_coords(rows: i32, columns: i32) -> UnGraph<(i32, i32), ()> {
     graph
 }
 
+// Edges become links between cells laid out on a rows x columns grid, then render as usual
+pub fn render<R: render::Renderer + ?Sized>(
+    graph: &UnGraph<(i32, i32), ()>,
+    columns: i32,
+    rows: i32,
+    renderer: &mut R,
+) {
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    link_edges(graph, &mut hgrid);
+    hgrid.render(renderer, render::Palette::Green);
+}
+
 pub fn to_png(
     graph: &UnGraph<(i32, i32), ()>,
     cell_size: i32,
@@ -52,32 +203,26 @@ pub fn to_png(
     rows: i32,
     filename: &str,
 ) -> Result<(), String> {
-    let img_width = cell_size * columns;
-    let img_height = cell_size * rows;
-    let mut dt = raqote::DrawTarget::new(img_width + cell_size * 2, img_height + cell_size * 2);
-
-    for node in graph.node_identifiers() {
-        let weight = graph.node_weight(node); // could also use the index: graph[node]
-        if let Some((rownum, colnum)) = weight {
-            let mut new_cell = cell::Cell::new(*rownum, *colnum);
-            let neighbor_iter = graph.neighbors(node);
-            for neighbor in neighbor_iter {
-                if let Some(neighbor_weight) = graph.node_weight(neighbor) {
-                    new_cell.add_neighbor(*neighbor_weight);
-                }
-            }
-            render::draw_cell(&mut dt, cell_size, new_cell);
-        }
-    }
-    dt.write_png(filename)
-        .map_err(|err| format!("Failed writing file {}", err))
+    let mut renderer = render::PngRenderer::new(
+        cell_size,
+        0.,
+        render::WallStyle::default(),
+        render::PathStyle::default(),
+    );
+    render(graph, columns, rows, &mut renderer);
+    renderer.finish(filename)
 }
 
 #[cfg(test)]
 mod test {
     use super::{base_from_coords, from_grid};
+    use super::{from_hashgrid, neighbor_graph, to_dot, to_graphml, to_hashgrid};
+    use crate::algorithms;
     use crate::grid::Grid;
+    use petgraph::algo::{connected_components, is_cyclic_undirected, min_spanning_tree};
+    use petgraph::data::FromElements;
     use petgraph::dot::{Config, Dot};
+    use petgraph::graph::{NodeIndex, UnGraph};
     #[test]
     fn test_from_coords() {}
     #[test]
@@ -92,7 +237,72 @@ mod test {
         let mut c3 = new_grid.get_item((1, 1)).unwrap().clone();
         c3.link((1, 0));
         new_grid.replace_cell(c3);
-        let graph = from_grid(new_grid);
+        let graph = from_grid(&new_grid);
         assert_eq!(graph.edge_count(), 3);
     }
+
+    #[test]
+    fn test_base_from_coords() {
+        let graph = base_from_coords(2, 3);
+        assert_eq!((graph.node_count(), graph.edge_count()), (6, 0));
+        let dot = format!("{:?}", Dot::with_config(&graph, &[Config::EdgeNoLabel]));
+        assert!(dot.contains("5 [ label = \"(1, 2)\" ]"));
+    }
+
+    #[test]
+    fn test_exports() {
+        let hgrid = algorithms::generate("sidewinder", 4, 5).unwrap();
+        let graph = from_hashgrid(&hgrid);
+        // a perfect maze is a spanning tree
+        assert_eq!((graph.node_count(), graph.edge_count()), (20, 19));
+
+        let dot = to_dot(&graph);
+        assert!(dot.starts_with("graph {"));
+        assert_eq!(dot.matches(" -- ").count(), 19);
+        assert!(dot.contains("label = \"3,4\" pos = \"144,-108!\""));
+
+        let graphml = to_graphml(&graph);
+        assert_eq!(graphml.matches("<node ").count(), 20);
+        assert_eq!(graphml.matches("<edge ").count(), 19);
+    }
+
+    #[test]
+    fn test_hashgrid_round_trip() {
+        let mut hgrid = algorithms::generate("aldous-broder", 6, 8).unwrap();
+        let graph = from_hashgrid(&hgrid);
+        assert_eq!(connected_components(&graph), 1);
+        assert!(!is_cyclic_undirected(&graph));
+        assert_eq!(
+            format!("{}", to_hashgrid(&graph).unwrap()),
+            format!("{}", hgrid)
+        );
+
+        algorithms::braid(&mut hgrid, 1.0);
+        let braided = from_hashgrid(&hgrid);
+        assert!(is_cyclic_undirected(&braided));
+        assert_eq!(
+            format!("{}", to_hashgrid(&braided).unwrap()),
+            format!("{}", hgrid)
+        );
+    }
+
+    #[test]
+    fn test_neighbor_graph_spanning_tree() {
+        let hgrid = algorithms::generate("sidewinder", 5, 7).unwrap();
+        let neighbors = neighbor_graph(&hgrid);
+        // horizontal pairs plus vertical pairs
+        assert_eq!(neighbors.edge_count(), 5 * 6 + 4 * 7);
+
+        let tree: UnGraph<(i32, i32), ()> = UnGraph::from_elements(min_spanning_tree(&neighbors));
+        let maze = to_hashgrid(&tree).unwrap();
+        assert_eq!((maze.rows, maze.columns), (5, 7));
+        assert_eq!(from_hashgrid(&maze).edge_count(), 34);
+        assert_eq!(connected_components(&from_hashgrid(&maze)), 1);
+
+        // the corners aren't neighbours
+        let mut skipping = tree;
+        let corners = (NodeIndex::new(0), NodeIndex::new(34));
+        skipping.add_edge(corners.0, corners.1, ());
+        assert!(to_hashgrid(&skipping).is_err());
+    }
 }
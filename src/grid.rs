@@ -1,7 +1,7 @@
 use crate::cell;
 use crate::render;
+use crate::render::Renderer;
 use rand::prelude::*;
-use raqote::DrawTarget;
 
 pub struct Neighbors {
     north_cell: (i32, i32),
@@ -134,23 +134,28 @@ impl Grid {
         self.grid.iter()
     }
 
-    pub fn to_png(&self, cell_size: i32, filename: &str) -> Result<(), String> {
-        let img_width: i32 = cell_size * self.columns;
-        let img_height: i32 = cell_size * self.rows;
-        let mut dt = DrawTarget::new(
-            (img_width + cell_size * 2i32) as i32,
-            (img_height + cell_size * 2i32) as i32,
+    pub fn render<R: render::Renderer + ?Sized>(&self, renderer: &mut R, palette: render::Palette) {
+        let cells: Vec<&cell::Cell> = self.iter().collect();
+        render::render_cells(
+            renderer,
+            &cells,
+            self.rows,
+            self.columns,
+            None,
+            palette,
+            None,
         );
+    }
 
-        for rownum in 0..self.rows {
-            for colnum in 0..self.columns {
-                // we pad it an extra + cell_size to keep it off from the edges
-                let some_cell = self.grid[rownum as usize][colnum as usize].clone();
-                render::draw_cell(&mut dt, cell_size, some_cell);
-            }
-        }
-        dt.write_png(filename)
-            .map_err(|err| format!("Failed writing file {}", err))
+    pub fn to_png(&self, cell_size: i32, filename: &str) -> Result<(), String> {
+        let mut renderer = render::PngRenderer::new(
+            cell_size,
+            0.,
+            render::WallStyle::default(),
+            render::PathStyle::default(),
+        );
+        self.render(&mut renderer, render::Palette::Green);
+        renderer.finish(filename)
     }
 }
 
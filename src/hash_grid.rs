@@ -1,10 +1,12 @@
 use rand::prelude::*;
-use raqote::DrawTarget;
 use std::collections::HashMap;
 
 use crate::cell;
 use crate::distances;
+use crate::grid;
 use crate::render;
+use crate::render::Renderer;
+use crate::theme;
 
 pub struct Neighbors {
     north_cell: (i32, i32),
@@ -22,12 +24,18 @@ pub fn get_neighbor_coords(current: (i32, i32)) -> Neighbors {
     }
 }
 
+// the two cells joined by a passage
+pub type Link = ((i32, i32), (i32, i32));
+
 #[derive(Eq, PartialEq, Debug)]
 pub struct HashGrid {
     pub rows: i32,
     pub columns: i32,
     grid: HashMap<(i32, i32), cell::Cell>,
     distances: Option<distances::DistanceMap>,
+    path: Option<Vec<(i32, i32)>>,
+    // every link in the order it was made, once recording is switched on
+    history: Option<Vec<Link>>,
 }
 
 impl HashGrid {
@@ -37,6 +45,8 @@ impl HashGrid {
             columns,
             grid: HashMap::new(),
             distances: None,
+            path: None,
+            history: None,
         };
         grd_init.prepare_grid().configure_cells();
         grd_init
@@ -52,13 +62,33 @@ impl HashGrid {
             columns: cells[0].len() as i32,
             grid: HashMap::new(),
             distances: None,
+            path: None,
+            history: None,
         };
-        for cll in cells.iter().flatten().into_iter() {
+        for cll in cells.iter().flatten() {
             grd_init.grid.insert((cll.row, cll.column), cll.to_owned());
         }
         grd_init
     }
 
+    // The Grid algorithms only record a link on the cell that made it, so we mirror each link here
+    pub fn from_grid(some_grid: &grid::Grid) -> Self {
+        let mut grd_init = Self::from_cells(some_grid.each_row().cloned().collect());
+        grd_init.link_from_grid(some_grid);
+        grd_init
+    }
+
+    // Replays the links from a Grid row by row, which is the order the Grid algorithms carve in
+    pub fn link_from_grid(&mut self, some_grid: &grid::Grid) {
+        for cll in some_grid.iter() {
+            let mut links: Vec<(i32, i32)> = cll.links.iter().copied().collect();
+            links.sort_unstable();
+            for key in links {
+                self.link_cells(cll.coords(), key);
+            }
+        }
+    }
+
     pub fn prepare_grid(&mut self) -> &mut Self {
         for rownum in 0..self.rows {
             for colnum in 0..self.columns {
@@ -123,34 +153,104 @@ impl HashGrid {
         Ok(())
     }
 
+    pub fn link_cells(&mut self, first: (i32, i32), second: (i32, i32)) {
+        if let Some(cll) = self.grid.get_mut(&first) {
+            cll.link(second);
+        }
+        if let Some(cll) = self.grid.get_mut(&second) {
+            cll.link(first);
+        }
+        if let Some(history) = self.history.as_mut() {
+            history.push((first, second));
+        }
+    }
+
+    pub fn record_links(&mut self) {
+        self.history = Some(vec![]);
+    }
+
+    pub fn history(&self) -> Option<&[Link]> {
+        self.history.as_deref()
+    }
+
+    // Dead ends are cells with exactly one passage out, in row-major order
+    pub fn deadends(&self) -> Vec<(i32, i32)> {
+        let mut found = vec![];
+        for rownum in 0..self.rows {
+            for colnum in 0..self.columns {
+                if let Some(cll) = self.grid.get(&(rownum, colnum)) {
+                    if cll.links.len() == 1 {
+                        found.push((rownum, colnum));
+                    }
+                }
+            }
+        }
+        found
+    }
+
     pub fn size(&self) -> i32 {
         self.rows * self.columns
     }
 
     pub fn random_cell(&self) -> Option<&cell::Cell> {
-        let mut rng = thread_rng();
+        self.random_cell_with_rng(&mut thread_rng())
+    }
+
+    pub fn random_cell_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&cell::Cell> {
         let rownum = rng.gen_range(0..self.rows);
         let colnum = rng.gen_range(0..self.columns);
         self.grid.get(&(rownum, colnum))
     }
 
-    pub fn to_png(&self, cell_size: i32, filename: &str) -> Result<(), String> {
-        let img_width: i32 = cell_size * self.columns;
-        let img_height: i32 = cell_size * self.rows;
-        let mut dt = DrawTarget::new(
-            (img_width + cell_size * 2i32) as i32,
-            (img_height + cell_size * 2i32) as i32,
+    // cells in row-major order, for anything which should come out the same on every run
+    pub fn cells(&self) -> Vec<&cell::Cell> {
+        (0..self.rows)
+            .flat_map(|rownum| {
+                (0..self.columns).map(move |colnum| self.grid.get(&(rownum, colnum)).unwrap())
+            })
+            .collect()
+    }
+
+    pub fn render<R: render::Renderer + ?Sized>(&self, renderer: &mut R, palette: render::Palette) {
+        let cells = self.cells();
+        render::render_cells(
+            renderer,
+            &cells,
+            self.rows,
+            self.columns,
+            self.distances.as_ref().map(|dist| &dist.map),
+            palette,
+            self.path.as_deref(),
         );
+    }
 
-        for rownum in 0..self.rows {
-            for colnum in 0..self.columns {
-                // we pad it an extra + cell_size to keep it off from the edges
-                let some_cell = self.grid.get(&(rownum, colnum)).unwrap();
-                render::draw_cell(&mut dt, cell_size, some_cell.to_owned());
-            }
-        }
-        dt.write_png(filename)
-            .map_err(|err| format!("Failed writing file {}", err))
+    pub fn to_png(
+        &self,
+        cell_size: i32,
+        theme: &theme::Theme,
+        filename: &str,
+    ) -> Result<(), String> {
+        let mut renderer =
+            render::PngRenderer::new(cell_size, 0., theme.wall_style(), theme.path_style());
+        renderer.set_background(theme.background);
+        self.render(&mut renderer, theme.palette);
+        renderer.finish(filename)
+    }
+
+    pub fn distances(&self) -> Option<&distances::DistanceMap> {
+        self.distances.as_ref()
+    }
+
+    pub fn set_distances(&mut self, distances: Option<distances::DistanceMap>) {
+        self.distances = distances;
+    }
+
+    pub fn path(&self) -> Option<&[(i32, i32)]> {
+        self.path.as_deref()
+    }
+
+    pub fn set_path(&mut self, path: Option<Vec<(i32, i32)>>) {
+        self.path = path;
     }
 
     pub fn build_distance_map(&mut self) {
@@ -163,7 +263,7 @@ impl HashGrid {
         let mut maxval = 0u32;
         let dm = distances::DistanceMap::from_hashgrid(start, self);
         // get the spot furthest away from root
-        let endpoint = dm.map.iter().fold((0i32, 0i32), |acc, val| {
+        let endpoint = dm.map.iter().fold(start, |acc, val| {
             if val.1 > &maxval {
                 maxval = *val.1;
                 *val.0
@@ -173,7 +273,8 @@ impl HashGrid {
         });
         // build a distance map with just those breadcrumbs
         let breadcrumbs: HashMap<(i32, i32), u32> = dm.path_to(endpoint, self);
-        self.distances = Some(distances::DistanceMap::new((0i32, 0i32), breadcrumbs));
+        self.path = Some(dm.path_cells(endpoint, self));
+        self.distances = Some(distances::DistanceMap::new(start, breadcrumbs));
     }
 
     pub fn get_cell_body(&self, cell_loc: &(i32, i32)) -> String {
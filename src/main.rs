@@ -1,12 +1,31 @@
-use clap::Parser;
+#![allow(dead_code)]
+use clap::{Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
 
 mod algorithms;
+mod analysis;
+mod animate;
+mod ascii;
+mod bench;
+mod binary;
 mod cell;
 mod distances;
 mod graph;
 mod grid;
 mod hash_grid;
+mod matrix;
+mod mesh;
+mod pdf;
+mod png_import;
 mod render;
+mod save;
+mod search;
+mod spanning;
+mod svg;
+mod theme;
+mod tilemap;
+mod validate;
 
 #[derive(Parser)]
 #[clap(version = "0.3.0", author = "Erik Aker <eraker@gmail.com> & Milo Aker")]
@@ -19,18 +38,365 @@ struct Opts {
     algorithm: String,
     #[clap(short, long)]
     outfile: Option<String>,
+    /// Output format for the outfile: png, svg, pdf, ascii, dot, graphml, stl, obj, tmx or tmj (defaults to the outfile's extension)
+    #[clap(short, long)]
+    format: Option<String>,
+    /// Wall stroke width
+    #[clap(long, default_value = "2.0")]
+    stroke_width: f32,
+    /// Wall line caps: round, square or butt
+    #[clap(long, default_value = "round")]
+    line_cap: render::LineCap,
+    /// Colour scheme: classic, blueprint, night, print, or the path to a TOML or JSON theme
+    #[clap(long, default_value = "classic")]
+    theme: String,
+    /// Wall colour, like #000000 or black (overrides the theme)
+    #[clap(long, parse(try_from_str = render::parse_colour))]
+    wall_colour: Option<(u8, u8, u8)>,
+    /// Wall opacity from 0 to 255 (overrides the theme)
+    #[clap(long)]
+    wall_alpha: Option<u8>,
+    /// Shrink each cell by this fraction of its size so passages become corridors (PNG only)
+    #[clap(long, default_value = "0.0", parse(try_from_str = parse_inset))]
+    inset: f32,
+    /// Background colour, like #ffffff or white (overrides the theme)
+    #[clap(long, parse(try_from_str = render::parse_colour))]
+    background: Option<(u8, u8, u8)>,
     #[clap(long)]
     with_distance_map: bool,
     #[clap(long)]
     with_breadcrumbs: bool,
+    /// Characters for printing the maze: classic, unicode or blocks
+    #[clap(long, default_value = "classic")]
+    ascii_style: ascii::AsciiStyle,
+    /// Colours for the distance map: green, heat, grayscale or rainbow (overrides the theme)
+    #[clap(long)]
+    palette: Option<render::Palette>,
+    /// Colour of the breadcrumb path, like #cc2222 or red (overrides the theme)
+    #[clap(long, parse(try_from_str = render::parse_colour))]
+    path_colour: Option<(u8, u8, u8)>,
+    #[clap(long, default_value = "4.0")]
+    path_width: f32,
+    #[clap(long, parse(try_from_str = parse_braid))]
+    braid: Option<f64>,
+    /// Cell size in millimetres for STL and OBJ output
+    #[clap(long, default_value = "10.0")]
+    mesh_cell_size: f32,
+    /// Wall height in millimetres for STL and OBJ output
+    #[clap(long, default_value = "8.0")]
+    wall_height: f32,
+    /// Wall thickness in millimetres for STL and OBJ output
+    #[clap(long, default_value = "1.6")]
+    wall_thickness: f32,
+    /// Base plate thickness in millimetres for STL and OBJ output
+    #[clap(long, default_value = "2.0")]
+    base_thickness: f32,
+    /// Tiles along each side of a cell in Tiled maps, including one for the wall
+    #[clap(long, default_value = "2")]
+    cell_tiles: usize,
+    /// Tile size in pixels for Tiled maps
+    #[clap(long, default_value = "16")]
+    tile_size: u32,
+    /// Tiled global tile IDs as wall,floor,start,goal,breadcrumb
+    #[clap(long, default_value = "1,2,3,4,5")]
+    tile_ids: tilemap::TileIds,
+    /// Mark the start, goal and breadcrumbs in Tiled maps (with --with-distance-map or --with-breadcrumbs)
+    #[clap(long)]
+    tile_marks: bool,
+    /// Tileset file referenced by Tiled maps
+    #[clap(long, default_value = "maze.tsx")]
+    tileset: String,
+    /// Seed for the random number generator, so the same maze can be made again
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Number of mazes on each page of PDF output, each with the next seed
+    #[clap(long, default_value = "1")]
+    per_page: usize,
+    /// Number of pages of mazes in PDF output
+    #[clap(long, default_value = "1")]
+    pages: usize,
+    /// Title printed at the top of each PDF page
+    #[clap(long)]
+    title: Option<String>,
+    /// Print each maze's seed under it in PDF output
+    #[clap(long)]
+    seed_caption: bool,
+    /// Follow each PDF page with a page showing the solutions
+    #[clap(long)]
+    solutions: bool,
+    /// Save the maze as JSON, or in the compact binary format for .maze files and large mazes
+    #[clap(long)]
+    save: Option<String>,
+    /// Load a maze saved with --save, drawn in ASCII or written as a PNG, instead of generating one
+    #[clap(long)]
+    load: Option<String>,
+    #[clap(long)]
+    stats: bool,
+    /// Write the maze as a CSV matrix of 1 for walls and 0 for floor
+    #[clap(long)]
+    export_matrix: Option<String>,
+    /// Write each cell's distance as a rows x columns CSV (from the bottom left cell if no map was built)
+    #[clap(long)]
+    export_distances: Option<String>,
+    #[clap(long)]
+    stats_json: bool,
+    /// Write an animated GIF of the maze being carved (an APNG if the name ends in .png)
+    #[clap(long)]
+    animate: Option<String>,
+    /// Write an animation of a solver searching from the top left to the bottom right cell
+    #[clap(long)]
+    animate_solver: Option<String>,
+    /// Search used by --animate-solver: bfs or astar
+    #[clap(long, default_value = "bfs")]
+    solver: search::Solver,
+    /// Number of links carved, or search steps taken, between animation frames
+    #[clap(long, default_value = "1")]
+    frame_skip: usize,
+    /// Delay between animation frames in hundredths of a second
+    #[clap(long, default_value = "5")]
+    frame_delay: u16,
+    #[clap(subcommand)]
+    subcmd: Option<SubCommand>,
+}
+
+// An inset of half the cell or more would leave nothing of the cell inside its walls
+fn parse_inset(value: &str) -> Result<f32, String> {
+    let inset: f32 = value
+        .parse()
+        .map_err(|err| format!("Failed parsing inset {}", err))?;
+    if !(0.0..0.5).contains(&inset) {
+        return Err(format!(
+            "Inset {} should be at least 0 and below 0.5",
+            inset
+        ));
+    }
+    Ok(inset)
+}
+
+fn parse_braid(value: &str) -> Result<f64, String> {
+    let braid: f64 = value
+        .parse()
+        .map_err(|err| format!("Failed parsing braid {}", err))?;
+    if !(0.0..=1.0).contains(&braid) {
+        return Err(format!("Braid {} should be between 0 and 1", braid));
+    }
+    Ok(braid)
+}
+
+#[derive(Subcommand)]
+enum SubCommand {
+    /// Generate many mazes with each algorithm and compare their statistics
+    BenchBias(BenchBias),
+    /// Generate mazes and check them for broken links, disconnected cells or loops
+    Validate(Validate),
+}
+
+#[derive(Parser)]
+struct BenchBias {
+    #[clap(short, long, default_value = "100")]
+    samples: u32,
+    #[clap(short, long, default_value = "10")]
+    rows: u8,
+    #[clap(short, long, default_value = "10")]
+    columns: u8,
+}
+
+#[derive(Parser)]
+struct Validate {
+    #[clap(short, long, default_value = "12")]
+    rows: u8,
+    #[clap(short, long, default_value = "12")]
+    columns: u8,
+    #[clap(short, long, default_value = "aldous-broder")]
+    algorithm: String,
+    #[clap(short, long, default_value = "1")]
+    samples: u32,
+    #[clap(long, parse(try_from_str = parse_braid))]
+    braid: Option<f64>,
+    /// Check a maze saved with --save, drawn in ASCII or written as a PNG instead of generating them
+    #[clap(long)]
+    load: Option<String>,
+    /// Also check the maze has no loops
+    #[clap(long)]
+    perfect: bool,
+}
+
+fn run_validate(args: Validate) {
+    if let Some(fname) = args.load.as_ref() {
+        let hgrid = match save::load_maze(fname) {
+            Ok((hgrid, _)) => hgrid,
+            Err(err) => {
+                println!("{} is invalid: {}", fname, err);
+                std::process::exit(1);
+            }
+        };
+        let violations = validate::validate(&hgrid, args.perfect);
+        if !violations.is_empty() {
+            println!("{} is invalid:", fname);
+            for violation in violations {
+                println!("  {}", violation);
+            }
+            std::process::exit(1);
+        }
+        return println!("{} is valid", fname);
+    }
+    let mut failed = false;
+    for sample in 0..args.samples {
+        let mut hgrid =
+            match algorithms::generate(&args.algorithm, args.rows as i32, args.columns as i32) {
+                Ok(hgrid) => hgrid,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            };
+        if let Some(braid_prob) = args.braid {
+            algorithms::braid(&mut hgrid, braid_prob);
+        }
+        let violations = validate::validate(&hgrid, args.perfect);
+        if !violations.is_empty() {
+            failed = true;
+            println!("Sample {} is invalid:", sample);
+            for violation in violations {
+                println!("  {}", violation);
+            }
+            println!("{}", hgrid);
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    println!(
+        "{} {} mazes are valid",
+        args.samples,
+        algorithms::algorithm_name(&args.algorithm)
+    );
+}
+
+fn run_bench_bias(args: BenchBias) {
+    let summaries: Vec<bench::BiasSummary> = algorithms::ALGORITHMS
+        .iter()
+        .map(|algorithm| {
+            bench::bench_bias(
+                algorithm,
+                args.samples,
+                args.rows as i32,
+                args.columns as i32,
+            )
+            .expect("Couldn't benchmark algorithm")
+        })
+        .collect();
+    println!(
+        "{} samples per algorithm on {}x{} grids",
+        args.samples, args.rows, args.columns
+    );
+    print!("{}", bench::format_table(&summaries));
+}
+
+fn seeded_maze(opts: &Opts, seed: u64, record: bool) -> hash_grid::HashGrid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut hgrid = hash_grid::HashGrid::new(opts.rows as i32, opts.columns as i32);
+    if record {
+        hgrid.record_links();
+    }
+    if let Err(err) = algorithms::carve_with_rng(&mut hgrid, &opts.algorithm, &mut rng) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    if let Some(braid_prob) = opts.braid {
+        algorithms::braid_with_rng(&mut hgrid, braid_prob, &mut rng);
+    }
+    hgrid
+}
+
+// The named theme or theme file, with any colours given on the command line on top
+fn build_theme(opts: &Opts) -> Result<theme::Theme, String> {
+    let mut theme = theme::Theme::select(&opts.theme)?;
+    if let Some(colour) = opts.background {
+        theme.background = Some(colour);
+    }
+    if let Some(colour) = opts.wall_colour {
+        theme.wall_colour = colour;
+    }
+    if let Some(alpha) = opts.wall_alpha {
+        theme.wall_alpha = alpha;
+    }
+    if let Some(colour) = opts.path_colour {
+        theme.path_colour = colour;
+    }
+    if let Some(palette) = opts.palette {
+        theme.palette = palette;
+    }
+    Ok(theme)
+}
+
+// The first maze on the first page is the one already made, the rest come from the seeds after
+// its own. A loaded maze may not have recorded a seed, so the rest start from a random one.
+fn write_pdf(
+    opts: &Opts,
+    hgrid: hash_grid::HashGrid,
+    seed: Option<u64>,
+    wall_style: render::WallStyle,
+    path_style: render::PathStyle,
+    fname: &str,
+) {
+    let first_seed = seed.unwrap_or_else(|| thread_rng().gen());
+    let mut first = Some(hgrid);
+    let mazes: Vec<pdf::BookMaze> = (0..opts.per_page.max(1) * opts.pages.max(1))
+        .map(|idx| {
+            let maze_seed = first_seed.wrapping_add(idx as u64);
+            let known = idx > 0 || seed.is_some();
+            pdf::BookMaze {
+                hgrid: first
+                    .take()
+                    .unwrap_or_else(|| seeded_maze(opts, maze_seed, false)),
+                caption: if opts.seed_caption && known {
+                    Some(format!("Seed {}", maze_seed))
+                } else {
+                    None
+                },
+            }
+        })
+        .collect();
+    let options = pdf::BookOptions {
+        per_page: opts.per_page,
+        title: opts.title.clone(),
+        solutions: opts.solutions,
+        // translucent walls print grey
+        wall_style: render::WallStyle {
+            alpha: 255,
+            ..wall_style
+        },
+        path_style,
+    };
+    pdf::write_book(fname, &mazes, &options).expect("Couldn't write file");
 }
 
 fn main() {
     let opts: Opts = Opts::parse();
-    let mut new_hgrid = hash_grid::HashGrid::new(opts.rows as i32, opts.columns as i32);
-    let hgrid = match opts.algorithm.as_str() {
-        "aldous-broder" => algorithms::aldous_broder(&mut new_hgrid),
-        _ => panic!("Unimplemented algorithm for hash grid"),
+    match opts.subcmd {
+        Some(SubCommand::BenchBias(args)) => return run_bench_bias(args),
+        Some(SubCommand::Validate(args)) => return run_validate(args),
+        None => (),
+    }
+    let theme = match build_theme(&opts) {
+        Ok(theme) => theme,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let (mut hgrid, metadata) = match opts.load.as_ref() {
+        Some(fname) => save::load_maze(fname).expect("Couldn't load maze"),
+        None => {
+            let seed = opts.seed.unwrap_or_else(|| thread_rng().gen());
+            let metadata = save::Metadata {
+                algorithm: Some(opts.algorithm.clone()),
+                seed: Some(seed),
+                braid: opts.braid,
+            };
+            (seeded_maze(&opts, seed, opts.animate.is_some()), metadata)
+        }
     };
     if opts.with_distance_map {
         hgrid.build_distance_map();
@@ -38,13 +404,188 @@ fn main() {
     if opts.with_breadcrumbs {
         hgrid.build_breadcrumbs_to_longest();
     }
-    println!("Aldous Broder");
-    println!("{}", hgrid);
+    println!(
+        "{}",
+        algorithms::algorithm_name(metadata.algorithm.as_deref().unwrap_or(&opts.algorithm))
+    );
+    if let Some(seed) = metadata.seed {
+        println!("Seed {}", seed);
+    }
+    let mut printer = ascii::AsciiRenderer::new(opts.ascii_style);
+    hgrid.render(&mut printer, theme.palette);
+    println!("{}", printer.text());
+    if opts.stats {
+        println!("{}", analysis::analyze(&hgrid));
+    }
+    if opts.stats_json {
+        let stats = analysis::analyze(&hgrid);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).expect("Couldn't serialize stats")
+        );
+    }
 
-    match opts.outfile {
-        None => (),
-        Some(fname) => hgrid
-            .to_png(30, fname.as_str())
-            .expect("Couldn't write file"),
+    if let Some(fname) = opts.save.as_ref() {
+        save::save_maze(&hgrid, &metadata, fname).expect("Couldn't save maze");
+    }
+    if let Some(fname) = opts.export_matrix.as_ref() {
+        let csv = matrix::walls_csv(&hgrid).expect("Couldn't build matrix");
+        std::fs::write(fname, csv).expect("Couldn't write matrix");
+    }
+    if let Some(fname) = opts.export_distances.as_ref() {
+        let csv = match hgrid.distances() {
+            Some(dm) => matrix::distances_csv(dm, hgrid.rows, hgrid.columns),
+            None => {
+                let dm = distances::DistanceMap::from_hashgrid((hgrid.rows - 1, 0), &hgrid);
+                matrix::distances_csv(&dm, hgrid.rows, hgrid.columns)
+            }
+        };
+        std::fs::write(fname, csv).expect("Couldn't write distances");
+    }
+
+    let wall_style = render::WallStyle {
+        width: opts.stroke_width,
+        cap: opts.line_cap,
+        ..theme.wall_style()
+    };
+    let path_style = render::PathStyle {
+        width: opts.path_width,
+        ..theme.path_style()
+    };
+    let animation_options = animate::AnimationOptions {
+        frame_skip: opts.frame_skip,
+        delay: opts.frame_delay,
+        ..animate::AnimationOptions::default()
     };
+    if let Some(fname) = opts.animate.as_ref() {
+        let history = match hgrid.history() {
+            Some(history) => history,
+            None => {
+                eprintln!("A loaded maze has no carve history to animate");
+                std::process::exit(1);
+            }
+        };
+        let mut renderer =
+            render::PngRenderer::new(30, opts.inset, wall_style.clone(), path_style.clone());
+        renderer.set_background(theme.background);
+        animate::animate_generation(
+            hgrid.rows,
+            hgrid.columns,
+            history,
+            &mut renderer,
+            &animation_options,
+            fname,
+        )
+        .expect("Couldn't write animation");
+    }
+    if let Some(fname) = opts.animate_solver.as_ref() {
+        let mut renderer =
+            render::PngRenderer::new(30, opts.inset, wall_style.clone(), path_style.clone());
+        renderer.set_background(theme.background);
+        animate::animate_search(
+            &hgrid,
+            opts.solver,
+            (0, 0),
+            (hgrid.rows - 1, hgrid.columns - 1),
+            &mut renderer,
+            &animation_options,
+            fname,
+        )
+        .expect("Couldn't write animation");
+    }
+
+    if let Some(fname) = opts.outfile.clone() {
+        let format = opts.format.clone().unwrap_or_else(|| {
+            std::path::Path::new(&fname)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("png")
+                .to_lowercase()
+        });
+        // a single maze without a title or captions is drawn on a page of its own size below
+        let book = opts.per_page > 1
+            || opts.pages > 1
+            || opts.title.is_some()
+            || opts.seed_caption
+            || opts.solutions;
+        if format == "pdf" && book {
+            return write_pdf(&opts, hgrid, metadata.seed, wall_style, path_style, &fname);
+        }
+        if format == "dot" || format == "graphml" {
+            let maze_graph = graph::from_hashgrid(&hgrid);
+            let text = if format == "dot" {
+                graph::to_dot(&maze_graph)
+            } else {
+                graph::to_graphml(&maze_graph)
+            };
+            std::fs::write(&fname, text).expect("Couldn't write file");
+            return;
+        }
+        if format == "stl" || format == "obj" {
+            let options = mesh::MeshOptions {
+                cell_size: opts.mesh_cell_size,
+                wall_height: opts.wall_height,
+                wall_thickness: opts.wall_thickness,
+                base_thickness: opts.base_thickness,
+            };
+            let mesh = mesh::from_hashgrid(&hgrid, &options).expect("Couldn't build mesh");
+            let text = if format == "obj" {
+                mesh.to_obj()
+            } else {
+                mesh.to_stl()
+            };
+            std::fs::write(&fname, text).expect("Couldn't write file");
+            return;
+        }
+        if format == "tmx" || format == "tmj" {
+            let options = tilemap::TilemapOptions {
+                cell_tiles: opts.cell_tiles,
+                tile_size: opts.tile_size,
+                ids: opts.tile_ids,
+                marks: opts.tile_marks,
+                tileset: opts.tileset.clone(),
+            };
+            let text = if format == "tmx" {
+                tilemap::to_tmx(&hgrid, &options)
+            } else {
+                tilemap::to_tiled_json(&hgrid, &options)
+            };
+            std::fs::write(&fname, text.expect("Couldn't build tilemap"))
+                .expect("Couldn't write file");
+            return;
+        }
+        let mut renderer: Box<dyn render::Renderer> = match format.as_str() {
+            "svg" => {
+                let style = svg::SvgStyle {
+                    stroke_width: opts.stroke_width,
+                    wall_colour: svg::hex(theme.wall_colour),
+                    wall_opacity: theme.wall_alpha as f32 / 255.,
+                    line_cap: opts.line_cap,
+                    background: theme.background.map(svg::hex),
+                };
+                Box::new(svg::SvgRenderer::new(30, style, path_style))
+            }
+            "png" => {
+                let mut png = render::PngRenderer::new(30, opts.inset, wall_style, path_style);
+                png.set_background(theme.background);
+                Box::new(png)
+            }
+            // translucent walls print grey
+            "pdf" => Box::new(pdf::PdfRenderer::new(
+                30,
+                render::WallStyle {
+                    alpha: 255,
+                    ..wall_style
+                },
+                path_style,
+            )),
+            "ascii" | "txt" => Box::new(ascii::AsciiRenderer::new(opts.ascii_style)),
+            other => {
+                eprintln!("Unsupported output format {}", other);
+                std::process::exit(1);
+            }
+        };
+        hgrid.render(renderer.as_mut(), theme.palette);
+        renderer.finish(&fname).expect("Couldn't write file");
+    }
 }
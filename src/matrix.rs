@@ -0,0 +1,81 @@
+use crate::distances;
+use crate::hash_grid;
+use crate::tilemap;
+
+// The maze as a block matrix, with 1 for a wall and 0 for floor. Each cell is a floor tile
+// with a wall tile on each side, so a rows x columns maze is (2 rows + 1) x (2 columns + 1).
+pub fn walls_csv(hgrid: &hash_grid::HashGrid) -> Result<String, String> {
+    let blocks = tilemap::blocks(hgrid, 2)?;
+    Ok(blocks
+        .iter()
+        .map(|row| {
+            let values: Vec<&str> = row
+                .iter()
+                .map(|tile| {
+                    if *tile == tilemap::Tile::Wall {
+                        "1"
+                    } else {
+                        "0"
+                    }
+                })
+                .collect();
+            values.join(",") + "\n"
+        })
+        .collect())
+}
+
+// One line per row of cells. Cells the map doesn't reach, like those off a breadcrumb path,
+// are left empty.
+pub fn distances_csv(dm: &distances::DistanceMap, rows: i32, columns: i32) -> String {
+    (0..rows)
+        .map(|row| {
+            let values: Vec<String> = (0..columns)
+                .map(|column| {
+                    dm.map
+                        .get(&(row, column))
+                        .map(|distance| distance.to_string())
+                        .unwrap_or_default()
+                })
+                .collect();
+            values.join(",") + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{distances_csv, walls_csv};
+    use crate::algorithms;
+    use crate::hash_grid::HashGrid;
+
+    #[test]
+    fn test_walls_csv() {
+        let mut hgrid = HashGrid::new(1, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        assert_eq!(
+            walls_csv(&hgrid).unwrap(),
+            "1,1,1,1,1\n1,0,0,0,1\n1,1,1,1,1\n"
+        );
+    }
+
+    #[test]
+    fn test_distances_csv() {
+        let mut hgrid = algorithms::generate("binary-tree", 3, 4).unwrap();
+        hgrid.build_distance_map();
+        let csv = distances_csv(hgrid.distances().unwrap(), 3, 4);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // the distance map starts from the bottom left cell
+        assert!(lines[2].starts_with("0,"));
+        assert!(lines.iter().all(|line| line.split(',').count() == 4));
+
+        hgrid.build_breadcrumbs_to_longest();
+        let path = hgrid.path().unwrap().len();
+        let csv = distances_csv(hgrid.distances().unwrap(), 3, 4);
+        let filled = csv
+            .split([',', '\n'])
+            .filter(|value| !value.is_empty())
+            .count();
+        assert_eq!(filled, path);
+    }
+}
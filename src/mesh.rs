@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use crate::hash_grid;
+use crate::render;
+
+// Sizes in millimetres
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshOptions {
+    pub cell_size: f32,
+    pub wall_height: f32,
+    pub wall_thickness: f32,
+    pub base_thickness: f32,
+}
+
+impl Default for MeshOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 10.,
+            wall_height: 8.,
+            wall_thickness: 1.6,
+            base_thickness: 2.,
+        }
+    }
+}
+
+pub struct Mesh {
+    pub vertices: Vec<[f32; 3]>,
+    // counter-clockwise seen from outside
+    pub triangles: Vec<[usize; 3]>,
+    lookup: HashMap<[u32; 3], usize>,
+}
+
+impl Mesh {
+    fn new() -> Self {
+        Self {
+            vertices: vec![],
+            triangles: vec![],
+            lookup: HashMap::new(),
+        }
+    }
+
+    // Shares vertices between faces, which is what keeps the OBJ watertight
+    fn vertex(&mut self, point: [f32; 3]) -> usize {
+        let key = [point[0].to_bits(), point[1].to_bits(), point[2].to_bits()];
+        let vertices = &mut self.vertices;
+        *self.lookup.entry(key).or_insert_with(|| {
+            vertices.push(point);
+            vertices.len() - 1
+        })
+    }
+
+    fn quad(&mut self, corners: [[f32; 3]; 4]) {
+        let idx = corners.map(|point| self.vertex(point));
+        self.triangles.push([idx[0], idx[1], idx[2]]);
+        self.triangles.push([idx[0], idx[2], idx[3]]);
+    }
+
+    fn normal(&self, triangle: &[usize; 3]) -> [f32; 3] {
+        let [a, b, c] = triangle.map(|idx| self.vertices[idx]);
+        let (u, v) = (
+            [b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+            [c[0] - a[0], c[1] - a[1], c[2] - a[2]],
+        );
+        let cross = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (cross[0].powi(2) + cross[1].powi(2) + cross[2].powi(2)).sqrt();
+        cross.map(|value| value / length)
+    }
+
+    pub fn to_stl(&self) -> String {
+        let mut stl = String::from("solid maze\n");
+        for triangle in self.triangles.iter() {
+            let normal = self.normal(triangle);
+            stl.push_str(&format!(
+                "  facet normal {} {} {}\n    outer loop\n",
+                normal[0], normal[1], normal[2]
+            ));
+            for idx in triangle.iter() {
+                let point = self.vertices[*idx];
+                stl.push_str(&format!(
+                    "      vertex {} {} {}\n",
+                    point[0], point[1], point[2]
+                ));
+            }
+            stl.push_str("    endloop\n  endfacet\n");
+        }
+        stl.push_str("endsolid maze\n");
+        stl
+    }
+
+    pub fn to_obj(&self) -> String {
+        let mut obj = String::from("o maze\n");
+        for point in self.vertices.iter() {
+            obj.push_str(&format!("v {} {} {}\n", point[0], point[1], point[2]));
+        }
+        for triangle in self.triangles.iter() {
+            obj.push_str(&format!(
+                "f {} {} {}\n",
+                triangle[0] + 1,
+                triangle[1] + 1,
+                triangle[2] + 1
+            ));
+        }
+        obj
+    }
+}
+
+// The plate is cut into rectangles along both sides of every grid line, giving a post at each
+// corner, a wall between each pair of posts and a floor inside each cell. Posts and walls that
+// are standing rise to the full height, and neighbouring rectangles share whole edges, so
+// joining their tops with upright faces leaves no gaps or overlaps.
+pub fn from_hashgrid(hgrid: &hash_grid::HashGrid, options: &MeshOptions) -> Result<Mesh, String> {
+    if options.wall_thickness <= 0. || options.wall_thickness >= options.cell_size {
+        return Err(format!(
+            "Walls {}mm thick don't fit in cells of {}mm",
+            options.wall_thickness, options.cell_size
+        ));
+    }
+    if options.wall_height <= 0. || options.base_thickness <= 0. {
+        return Err("Walls and the base need some height".to_string());
+    }
+    let (rows, columns) = (hgrid.rows as usize, hgrid.columns as usize);
+    let half = options.wall_thickness / 2.;
+    // edges of the rectangles, from the outside of the first grid line to the outside of the last
+    let edges = |lines: usize| -> Vec<f32> {
+        (0..=lines)
+            .flat_map(|line| {
+                let centre = line as f32 * options.cell_size;
+                [centre - half, centre + half]
+            })
+            .collect()
+    };
+    let xs = edges(columns);
+    // y runs up the print, so the top row of the maze is the far side of the plate
+    let ys: Vec<f32> = edges(rows)
+        .into_iter()
+        .map(|y| rows as f32 * options.cell_size - y)
+        .collect();
+
+    let floor = options.base_thickness;
+    let top = floor + options.wall_height;
+    let mut heights = vec![vec![floor; xs.len() - 1]; ys.len() - 1];
+    // posts always stand, even where no walls meet them
+    for spans in heights.iter_mut().step_by(2) {
+        for height in spans.iter_mut().step_by(2) {
+            *height = top;
+        }
+    }
+    // the same walls every renderer draws, measured in cells
+    for cll in hgrid.cells() {
+        for wall in render::cell_walls(cll, 1) {
+            // cell_walls pads the grid by one cell
+            let (x1, y1) = (wall.x1 as usize - 1, wall.y1 as usize - 1);
+            let (x2, y2) = (wall.x2 as usize - 1, wall.y2 as usize - 1);
+            if y1 == y2 {
+                heights[y1 * 2][x1.min(x2) * 2 + 1] = top;
+            } else {
+                heights[y1.min(y2) * 2 + 1][x1 * 2] = top;
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new();
+    let (last_row, last_column) = (heights.len() - 1, heights[0].len() - 1);
+    for (row, spans) in heights.iter().enumerate() {
+        // ys shrink going down the rows, so the row's near edge is its second one
+        let (near, far) = (ys[row + 1], ys[row]);
+        for (column, height) in spans.iter().copied().enumerate() {
+            let (left, right) = (xs[column], xs[column + 1]);
+            mesh.quad([
+                [left, near, height],
+                [right, near, height],
+                [right, far, height],
+                [left, far, height],
+            ]);
+            mesh.quad([
+                [left, near, 0.],
+                [left, far, 0.],
+                [right, far, 0.],
+                [right, near, 0.],
+            ]);
+
+            // the face on the right, up from whichever side is lower
+            let right_height = if column == last_column {
+                0.
+            } else {
+                spans[column + 1]
+            };
+            if height > right_height {
+                mesh.quad([
+                    [right, near, right_height],
+                    [right, far, right_height],
+                    [right, far, height],
+                    [right, near, height],
+                ]);
+            } else if right_height > height {
+                mesh.quad([
+                    [right, near, height],
+                    [right, near, right_height],
+                    [right, far, right_height],
+                    [right, far, height],
+                ]);
+            }
+            if column == 0 {
+                mesh.quad([
+                    [left, near, 0.],
+                    [left, near, height],
+                    [left, far, height],
+                    [left, far, 0.],
+                ]);
+            }
+
+            // the face towards the next row
+            let next_height = if row == last_row {
+                0.
+            } else {
+                heights[row + 1][column]
+            };
+            if height > next_height {
+                mesh.quad([
+                    [left, near, next_height],
+                    [right, near, next_height],
+                    [right, near, height],
+                    [left, near, height],
+                ]);
+            } else if next_height > height {
+                mesh.quad([
+                    [left, near, height],
+                    [left, near, next_height],
+                    [right, near, next_height],
+                    [right, near, height],
+                ]);
+            }
+            if row == 0 {
+                mesh.quad([
+                    [left, far, 0.],
+                    [left, far, height],
+                    [right, far, height],
+                    [right, far, 0.],
+                ]);
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_hashgrid, MeshOptions};
+    use crate::algorithms;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_mesh_is_watertight() {
+        let mut hgrid = algorithms::generate("sidewinder", 4, 6).unwrap();
+        algorithms::braid(&mut hgrid, 0.5);
+        let mesh = from_hashgrid(&hgrid, &MeshOptions::default()).unwrap();
+        // every edge is used once in each direction by the triangles either side of it
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+        for triangle in mesh.triangles.iter() {
+            for idx in 0..3 {
+                let edge = (triangle[idx], triangle[(idx + 1) % 3]);
+                *edges.entry(edge).or_insert(0) += 1;
+            }
+        }
+        for ((from, to), count) in edges.iter() {
+            assert_eq!(*count, 1, "{:?}", (from, to));
+            assert_eq!(edges.get(&(*to, *from)), Some(&1), "{:?}", (from, to));
+        }
+        // a closed solid has a positive volume when its faces point outwards
+        let volume: f32 = mesh
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|idx| mesh.vertices[idx]);
+                (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                    / 6.
+            })
+            .sum();
+        let plate = (6. * 10. + 1.6) * (4. * 10. + 1.6) * 2.;
+        assert!(volume > plate, "{}", volume);
+    }
+
+    #[test]
+    fn test_rejects_thick_walls() {
+        let hgrid = algorithms::generate("sidewinder", 2, 2).unwrap();
+        let options = MeshOptions {
+            wall_thickness: 12.,
+            ..MeshOptions::default()
+        };
+        assert!(from_hashgrid(&hgrid, &options).is_err());
+    }
+}
@@ -0,0 +1,475 @@
+use std::fs;
+
+use crate::cell;
+use crate::distances;
+use crate::hash_grid;
+use crate::render;
+use crate::render::Renderer;
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.;
+const PAGE_HEIGHT: f32 = 842.;
+const MARGIN: f32 = 40.;
+const TITLE_SIZE: f32 = 18.;
+const CAPTION_SIZE: f32 = 10.;
+
+fn rgb(colour: (u8, u8, u8)) -> String {
+    format!(
+        "{:.3} {:.3} {:.3}",
+        colour.0 as f32 / 255.,
+        colour.1 as f32 / 255.,
+        colour.2 as f32 / 255.
+    )
+}
+
+fn cap_style(cap: render::LineCap) -> u8 {
+    match cap {
+        render::LineCap::Butt => 0,
+        render::LineCap::Round => 1,
+        render::LineCap::Square => 2,
+    }
+}
+
+// The characters WinAnsiEncoding puts in 0x80 to 0x9f, where Latin-1 has control codes
+const WIN_ANSI_EXTRAS: [(char, u8); 27] = [
+    ('€', 0x80),
+    ('‚', 0x82),
+    ('ƒ', 0x83),
+    ('„', 0x84),
+    ('…', 0x85),
+    ('†', 0x86),
+    ('‡', 0x87),
+    ('ˆ', 0x88),
+    ('‰', 0x89),
+    ('Š', 0x8a),
+    ('‹', 0x8b),
+    ('Œ', 0x8c),
+    ('Ž', 0x8e),
+    ('‘', 0x91),
+    ('’', 0x92),
+    ('“', 0x93),
+    ('”', 0x94),
+    ('•', 0x95),
+    ('–', 0x96),
+    ('—', 0x97),
+    ('˜', 0x98),
+    ('™', 0x99),
+    ('š', 0x9a),
+    ('›', 0x9b),
+    ('œ', 0x9c),
+    ('ž', 0x9e),
+    ('Ÿ', 0x9f),
+];
+
+// Strings in content streams are wrapped in parentheses, so those and backslashes need escaping.
+// Helvetica reads them as WinAnsi, so anything past ASCII is written as an octal escape of its
+// WinAnsi code, and characters it has no code for become question marks.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for chr in text.chars() {
+        let code = match chr {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(chr);
+                continue;
+            }
+            ' '..='~' => {
+                escaped.push(chr);
+                continue;
+            }
+            '\u{a0}'..='\u{ff}' => Some(chr as u8),
+            _ => WIN_ANSI_EXTRAS
+                .iter()
+                .find(|(extra, _)| *extra == chr)
+                .map(|(_, code)| *code),
+        };
+        match code {
+            Some(code) => escaped.push_str(&format!("\\{:03o}", code)),
+            None => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+// Draws one maze as PDF content stream operators. Coordinates are the same pixels the other
+// renderers use, with y going down, so the page layout flips them when placing the maze.
+pub struct PdfRenderer {
+    cell_size: i32,
+    wall_style: render::WallStyle,
+    path_style: render::PathStyle,
+    width: i32,
+    height: i32,
+    content: String,
+    walls: String,
+}
+
+impl PdfRenderer {
+    pub fn new(
+        cell_size: i32,
+        wall_style: render::WallStyle,
+        path_style: render::PathStyle,
+    ) -> Self {
+        Self {
+            cell_size,
+            wall_style,
+            path_style,
+            width: 0,
+            height: 0,
+            content: String::new(),
+            walls: String::new(),
+        }
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn content(&mut self) -> &str {
+        self.stroke_walls();
+        &self.content
+    }
+
+    fn stroke_walls(&mut self) {
+        if self.walls.is_empty() {
+            return;
+        }
+        self.content.push_str(&format!(
+            "{} RG {} w {} J 1 j\n{}S\n",
+            rgb(self.wall_style.colour),
+            self.wall_style.width,
+            cap_style(self.wall_style.cap),
+            self.walls
+        ));
+        self.walls.clear();
+    }
+}
+
+impl render::Renderer for PdfRenderer {
+    fn begin(&mut self, rows: i32, columns: i32) {
+        self.width = self.cell_size * columns + self.cell_size * 2;
+        self.height = self.cell_size * rows + self.cell_size * 2;
+        self.content.clear();
+        self.walls.clear();
+    }
+
+    fn fill_cell(&mut self, cll: &cell::Cell, _distance: u32, colour: (u8, u8, u8)) {
+        let coords = render::cell_box(cll, self.cell_size);
+        self.content.push_str(&format!(
+            "{} rg {} {} {} {} re f\n",
+            rgb(colour),
+            coords.x1,
+            coords.y1,
+            coords.x2 - coords.x1,
+            coords.y2 - coords.y1
+        ));
+    }
+
+    fn draw_wall(&mut self, wall: &render::Wall) {
+        self.walls.push_str(&format!(
+            "{} {} m {} {} l\n",
+            wall.x1, wall.y1, wall.x2, wall.y2
+        ));
+    }
+
+    fn draw_path(&mut self, path: &[(i32, i32)]) {
+        self.stroke_walls();
+        let mut ops = format!(
+            "{} RG {} w 1 J 1 j\n",
+            rgb(self.path_style.colour),
+            self.path_style.width
+        );
+        for (idx, coords) in path.iter().enumerate() {
+            let (x, y) = render::cell_centre(*coords, self.cell_size);
+            ops.push_str(&format!(
+                "{} {} {}\n",
+                x,
+                y,
+                if idx == 0 { "m" } else { "l" }
+            ));
+        }
+        ops.push_str("S\n");
+        self.content.push_str(&ops);
+    }
+
+    // a circle from four bezier quarters
+    fn draw_marker(&mut self, coords: (i32, i32), marker: render::Marker) {
+        self.stroke_walls();
+        let colour = match marker {
+            render::Marker::Start => self.path_style.start_colour,
+            render::Marker::Goal => self.path_style.goal_colour,
+        };
+        let (x, y) = render::cell_centre(coords, self.cell_size);
+        let r = self.cell_size as f32 / 4.;
+        let k = r * 0.5523;
+        let mut ops = format!("{} rg\n{} {} m\n", rgb(colour), x + r, y);
+        let quarters = [
+            [(x + r, y + k), (x + k, y + r), (x, y + r)],
+            [(x - k, y + r), (x - r, y + k), (x - r, y)],
+            [(x - r, y - k), (x - k, y - r), (x, y - r)],
+            [(x + k, y - r), (x + r, y - k), (x + r, y)],
+        ];
+        for [first, second, end] in quarters.iter() {
+            ops.push_str(&format!(
+                "{} {} {} {} {} {} c\n",
+                first.0, first.1, second.0, second.1, end.0, end.1
+            ));
+        }
+        ops.push_str("f\n");
+        self.content.push_str(&ops);
+    }
+
+    fn finish(&mut self, filename: &str) -> Result<(), String> {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let page = format!("q 1 0 0 -1 0 {} cm\n{}Q\n", height, self.content());
+        fs::write(filename, document(&[page], width, height))
+            .map_err(|err| format!("Failed writing file {}", err))
+    }
+
+    fn cell_size(&self) -> i32 {
+        self.cell_size
+    }
+}
+
+// Builds a PDF with one page per content stream, all the same size, with Helvetica as F1.
+// Text is escaped down to ASCII, so the lengths of the streams are the same in bytes.
+fn document(pages: &[String], width: f32, height: f32) -> Vec<u8> {
+    // the catalog, page tree and font come first, then a page and its contents for each page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|idx| 4 + idx * 2).collect();
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<String>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (idx, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            width,
+            height,
+            page_ids[idx] + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = vec![];
+    for (idx, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", idx + 1, object));
+    }
+    let xref = out.len();
+    out.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    out.into_bytes()
+}
+
+pub struct BookMaze {
+    pub hgrid: hash_grid::HashGrid,
+    pub caption: Option<String>,
+}
+
+pub struct BookOptions {
+    pub per_page: usize,
+    pub title: Option<String>,
+    // follow each page of mazes with a page showing their solutions
+    pub solutions: bool,
+    pub wall_style: render::WallStyle,
+    pub path_style: render::PathStyle,
+}
+
+fn text(x: f32, y: f32, size: f32, line: &str) -> String {
+    format!(
+        "BT /F1 {} Tf {} {} Td ({}) Tj ET\n",
+        size,
+        x,
+        y,
+        escape_text(line)
+    )
+}
+
+// Helvetica averages a little over half an em per character, which is close enough to centre
+fn centred_text(centre: f32, y: f32, size: f32, line: &str) -> String {
+    text(
+        centre - line.chars().count() as f32 * size * 0.26,
+        y,
+        size,
+        line,
+    )
+}
+
+// Lays the mazes out in a grid of slots on each page, scaled to fit and centred in their slot
+fn book_page(
+    mazes: &[BookMaze],
+    options: &BookOptions,
+    title: Option<&str>,
+    solved: bool,
+) -> String {
+    let mut content = String::new();
+    let mut top = PAGE_HEIGHT - MARGIN;
+    if let Some(title) = title {
+        content.push_str(&centred_text(
+            PAGE_WIDTH / 2.,
+            top - TITLE_SIZE,
+            TITLE_SIZE,
+            title,
+        ));
+        top -= TITLE_SIZE * 2.5;
+    }
+
+    let per_page = options.per_page.max(1);
+    let slot_columns = (per_page as f32).sqrt().ceil() as usize;
+    let slot_rows = per_page.div_ceil(slot_columns);
+    let slot_width = (PAGE_WIDTH - MARGIN * 2.) / slot_columns as f32;
+    let slot_height = (top - MARGIN) / slot_rows as f32;
+
+    for (idx, maze) in mazes.iter().enumerate() {
+        let hgrid = &maze.hgrid;
+        let mut renderer =
+            PdfRenderer::new(30, options.wall_style.clone(), options.path_style.clone());
+        renderer.begin(hgrid.rows, hgrid.columns);
+        render::draw_walls(&mut renderer, &hgrid.cells());
+        let start = (0, 0);
+        let goal = (hgrid.rows - 1, hgrid.columns - 1);
+        let dm = distances::DistanceMap::from_hashgrid(start, hgrid);
+        if solved && dm.map.contains_key(&goal) {
+            render::draw_route(&mut renderer, &dm.path_cells(goal, hgrid));
+        } else {
+            renderer.draw_marker(start, render::Marker::Start);
+            renderer.draw_marker(goal, render::Marker::Goal);
+        }
+
+        let caption_height = if maze.caption.is_some() {
+            CAPTION_SIZE * 2.
+        } else {
+            0.
+        };
+        let (width, height) = renderer.size();
+        let scale = (slot_width / width as f32).min((slot_height - caption_height) / height as f32);
+        let slot_left = MARGIN + (idx % slot_columns) as f32 * slot_width;
+        let slot_top = top - (idx / slot_columns) as f32 * slot_height;
+        let left = slot_left + (slot_width - width as f32 * scale) / 2.;
+        content.push_str(&format!(
+            "q {} 0 0 {} {} {} cm\n{}Q\n",
+            scale,
+            -scale,
+            left,
+            slot_top,
+            renderer.content()
+        ));
+        if let Some(caption) = maze.caption.as_ref() {
+            content.push_str(&centred_text(
+                slot_left + slot_width / 2.,
+                slot_top - height as f32 * scale - CAPTION_SIZE * 1.2,
+                CAPTION_SIZE,
+                caption,
+            ));
+        }
+    }
+    content
+}
+
+pub fn write_book(filename: &str, mazes: &[BookMaze], options: &BookOptions) -> Result<(), String> {
+    let mut pages = vec![];
+    let solutions_title = match options.title.as_ref() {
+        Some(title) => format!("{} - Solutions", title),
+        None => "Solutions".to_string(),
+    };
+    for chunk in mazes.chunks(options.per_page.max(1)) {
+        pages.push(book_page(chunk, options, options.title.as_deref(), false));
+        if options.solutions {
+            pages.push(book_page(chunk, options, Some(&solutions_title), true));
+        }
+    }
+    fs::write(filename, document(&pages, PAGE_WIDTH, PAGE_HEIGHT))
+        .map_err(|err| format!("Failed writing file {}", err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_text, write_book, BookMaze, BookOptions, PdfRenderer};
+    use crate::algorithms;
+    use crate::hash_grid::HashGrid;
+    use crate::render;
+
+    #[test]
+    fn test_pdf_walls() {
+        let mut hgrid = HashGrid::new(1, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        let mut renderer = PdfRenderer::new(
+            10,
+            render::WallStyle::default(),
+            render::PathStyle::default(),
+        );
+        hgrid.render(&mut renderer, render::Palette::Green);
+        let content = renderer.content().to_string();
+        // the same six walls as the SVG output
+        assert_eq!(content.matches(" l\n").count(), 6);
+        assert!(content.contains("10 10 m 10 20 l\n"));
+        assert!(content.ends_with("S\n"));
+    }
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("Maze (1)"), "Maze \\(1\\)");
+        // é is 0xe9 in WinAnsi, the en dash 0x96, and Helvetica has no glyph for the arrow
+        assert_eq!(escape_text("Café – 1 → 2"), "Caf\\351 \\226 1 ? 2");
+    }
+
+    #[test]
+    fn test_book_pages() {
+        let mazes: Vec<BookMaze> = (0..3)
+            .map(|idx| BookMaze {
+                hgrid: algorithms::generate("sidewinder", 4, 5).unwrap(),
+                caption: Some(format!("Seed {}", idx)),
+            })
+            .collect();
+        let options = BookOptions {
+            per_page: 2,
+            title: Some("Mazes (vol. 1)".to_string()),
+            solutions: true,
+            wall_style: render::WallStyle::default(),
+            path_style: render::PathStyle::default(),
+        };
+        let fname = std::env::temp_dir().join("mazes-book-test.pdf");
+        write_book(fname.to_str().unwrap(), &mazes, &options).unwrap();
+        let bytes = std::fs::read(&fname).unwrap();
+        std::fs::remove_file(fname).unwrap();
+        let doc = String::from_utf8(bytes).unwrap();
+
+        assert!(doc.starts_with("%PDF-1.4\n"));
+        // two pages of puzzles, each followed by its solutions
+        assert!(doc.contains("/Count 4"));
+        assert_eq!(doc.matches("(Seed ").count(), 6);
+        assert!(doc.contains("(Mazes \\(vol. 1\\) - Solutions)"));
+
+        // every xref entry should point at the start of its object
+        let xref = doc.rfind("xref\n").unwrap();
+        let entries: Vec<&str> = doc[xref..].lines().skip(3).take(11).collect();
+        for (idx, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(doc[offset..].starts_with(&format!("{} 0 obj", idx + 1)));
+        }
+    }
+}
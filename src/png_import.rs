@@ -0,0 +1,245 @@
+use std::fs::File;
+
+use crate::hash_grid;
+
+struct Image {
+    width: i32,
+    height: i32,
+    // RGB after compositing onto white
+    pixels: Vec<(u8, u8, u8)>,
+}
+
+impl Image {
+    fn pixel(&self, x: i32, y: i32) -> (u8, u8, u8) {
+        let x = x.max(0).min(self.width - 1);
+        let y = y.max(0).min(self.height - 1);
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+fn decode(filename: &str) -> Result<Image, String> {
+    let file = File::open(filename).map_err(|err| format!("Failed reading file {}", err))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| format!("Failed decoding png {}", err))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|err| format!("Failed decoding png {}", err))?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err("Indexed png wasn't expanded".to_string()),
+    };
+    let over_white = |value: u8, alpha: u8| -> u8 {
+        (value as u32 * alpha as u32 / 255 + (255 - alpha as u32)) as u8
+    };
+    let pixels = buf
+        .chunks(channels)
+        .take((info.width * info.height) as usize)
+        .map(|px| match channels {
+            1 => (px[0], px[0], px[0]),
+            2 => {
+                let grey = over_white(px[0], px[1]);
+                (grey, grey, grey)
+            }
+            3 => (px[0], px[1], px[2]),
+            _ => (
+                over_white(px[0], px[3]),
+                over_white(px[1], px[3]),
+                over_white(px[2], px[3]),
+            ),
+        })
+        .collect();
+    Ok(Image {
+        width: info.width as i32,
+        height: info.height as i32,
+        pixels,
+    })
+}
+
+// A wall covers the pixels either side of the line between two cells. Where it's missing, each of
+// those pixels matches the floor further into its own cell, even under distance colours or the
+// path. Walls and floors line up with whole pixels, so even a wall barely lighter than the floor
+// on both sides, like white walls over the end of a grayscale map, changes them.
+fn has_wall(image: &Image, near: [(i32, i32); 2], far: [(i32, i32); 2]) -> bool {
+    near.iter()
+        .zip(far.iter())
+        .any(|(near, far)| image.pixel(near.0, near.1) != image.pixel(far.0, far.1))
+}
+
+// The first column from the left that isn't the margin's background, which is where the outer
+// wall starts on the rows between its corners
+fn wall_start(image: &Image) -> Option<i32> {
+    let background = image.pixel(0, 0);
+    (0..image.height)
+        .filter_map(|y| (0..image.width).find(|&x| image.pixel(x, y) != background))
+        .min()
+}
+
+// main draws the margin as one cell, so the outer wall sits on the line a cell in. Its stroke is
+// thinner than a cell, which puts that line on the first size past where the wall starts that
+// divides both sides of the image.
+fn detect_cell_size(image: &Image) -> Result<i32, String> {
+    let start = wall_start(image).ok_or_else(|| "No walls found in the png".to_string())?;
+    ((start + 1)..=(start * 2))
+        .find(|size| image.width % size == 0 && image.height % size == 0)
+        .ok_or_else(|| {
+            format!(
+                "Couldn't find a cell size for a {}x{} image",
+                image.width, image.height
+            )
+        })
+}
+
+fn from_image(image: &Image, cell_size: i32, margin: i32) -> Result<hash_grid::HashGrid, String> {
+    if cell_size <= 0 {
+        return Err(format!("Invalid cell size {}", cell_size));
+    }
+    let (inner_width, inner_height) = (image.width - margin * 2, image.height - margin * 2);
+    if inner_width <= 0
+        || inner_height <= 0
+        || inner_width % cell_size != 0
+        || inner_height % cell_size != 0
+    {
+        return Err(format!(
+            "A {}x{} image doesn't fit cells of {} with a margin of {}",
+            image.width, image.height, cell_size, margin
+        ));
+    }
+    let (rows, columns) = (inner_height / cell_size, inner_width / cell_size);
+    // the outer wall reaches as far past its line as any other wall, so moving each pixel beside
+    // the line out by that much clears every wall while staying short of the start and goal
+    // markers, however thick the stroke
+    let offset = wall_start(image).map_or(1, |start| (margin - start).max(1));
+    let half = cell_size / 2;
+
+    let mut hgrid = hash_grid::HashGrid::new(rows, columns);
+    for rownum in 0..rows {
+        for colnum in 0..columns {
+            let left = margin + colnum * cell_size;
+            let top = margin + rownum * cell_size;
+            if colnum + 1 < columns {
+                let (x, y) = (left + cell_size, top + half);
+                if !has_wall(
+                    image,
+                    [(x - 1, y), (x, y)],
+                    [(x - 1 - offset, y), (x + offset, y)],
+                ) {
+                    hgrid.link_cells((rownum, colnum), (rownum, colnum + 1));
+                }
+            }
+            if rownum + 1 < rows {
+                let (x, y) = (left + half, top + cell_size);
+                if !has_wall(
+                    image,
+                    [(x, y - 1), (x, y)],
+                    [(x, y - 1 - offset), (x, y + offset)],
+                ) {
+                    hgrid.link_cells((rownum, colnum), (rownum + 1, colnum));
+                }
+            }
+        }
+    }
+    Ok(hgrid)
+}
+
+// Rebuilds a maze from a PNG drawn without an inset, given its cell size and the margin around it
+pub fn from_png(
+    filename: &str,
+    cell_size: i32,
+    margin: i32,
+) -> Result<hash_grid::HashGrid, String> {
+    from_image(&decode(filename)?, cell_size, margin)
+}
+
+// Rebuilds a maze from a PNG drawn the way main draws it, working out the cell size from the image
+pub fn from_drawn_png(filename: &str) -> Result<hash_grid::HashGrid, String> {
+    let image = decode(filename)?;
+    let cell_size = detect_cell_size(&image)?;
+    from_image(&image, cell_size, cell_size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_drawn_png, from_png};
+    use crate::algorithms;
+    use crate::render::{self, Renderer};
+    use crate::theme::Theme;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_to_png_round_trip() {
+        let fname = std::env::temp_dir().join("mazes-import-test.png");
+        let fname = fname.to_str().unwrap();
+        let mut rng = StdRng::seed_from_u64(44);
+        for (idx, theme) in ["classic", "night", "blueprint"].iter().enumerate() {
+            let algorithm = algorithms::ALGORITHMS[idx];
+            let mut hgrid = algorithms::generate_with_rng(algorithm, 9, 11, &mut rng).unwrap();
+            algorithms::braid_with_rng(&mut hgrid, 0.3, &mut rng);
+            // distance colours, the path and its markers all have to be looked past
+            if idx == 0 {
+                hgrid.build_distance_map();
+            } else {
+                hgrid.build_breadcrumbs_to_longest();
+            }
+            hgrid
+                .to_png(20, &Theme::named(theme).unwrap(), fname)
+                .unwrap();
+
+            let imported = from_png(fname, 20, 20).unwrap();
+            assert_eq!((imported.rows, imported.columns), (9, 11));
+            for cll in hgrid.cells() {
+                assert_eq!(
+                    imported.get_item(cll.coords()).unwrap().links,
+                    cll.links,
+                    "{} {:?}",
+                    theme,
+                    cll.coords()
+                );
+            }
+        }
+        assert!(from_png(fname, 7, 20).is_err());
+        std::fs::remove_file(fname).unwrap();
+    }
+
+    #[test]
+    fn test_thick_walls_and_other_cell_sizes() {
+        let fname = std::env::temp_dir().join("mazes-import-thick-test.png");
+        let fname = fname.to_str().unwrap();
+        let mut rng = StdRng::seed_from_u64(45);
+        let theme = Theme::named("night").unwrap();
+        for (cell_size, stroke) in [(30, 2.), (30, 12.), (24, 9.), (40, 3.)].iter() {
+            let mut hgrid = algorithms::generate_with_rng("wilsons", 6, 8, &mut rng).unwrap();
+            algorithms::braid_with_rng(&mut hgrid, 0.3, &mut rng);
+            hgrid.build_breadcrumbs_to_longest();
+            let wall_style = render::WallStyle {
+                width: *stroke,
+                ..theme.wall_style()
+            };
+            let mut png = render::PngRenderer::new(*cell_size, 0., wall_style, theme.path_style());
+            png.set_background(theme.background);
+            hgrid.render(&mut png, theme.palette);
+            png.finish(fname).unwrap();
+
+            let imported = from_drawn_png(fname).unwrap();
+            assert_eq!((imported.rows, imported.columns), (6, 8));
+            for cll in hgrid.cells() {
+                assert_eq!(
+                    imported.get_item(cll.coords()).unwrap().links,
+                    cll.links,
+                    "{} {} {:?}",
+                    cell_size,
+                    stroke,
+                    cll.coords()
+                );
+            }
+        }
+        std::fs::remove_file(fname).unwrap();
+    }
+}
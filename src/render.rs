@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use crate::cell;
+use crate::theme;
 
 #[derive(Debug)]
 pub struct BoxCoords {
@@ -8,65 +11,613 @@ pub struct BoxCoords {
     pub y2: f32,
 }
 
-pub fn draw_cell(
-    dt: &mut raqote::DrawTarget,
-    cell_size: i32,
-    cll: cell::Cell,
-) -> &mut raqote::DrawTarget {
-    let mut pb = raqote::PathBuilder::new();
-    // if we can make a Cell and BoxCoords out of a node, then we can reuse rendering stuff
-    let coords = BoxCoords {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+    Green,
+    Heat,
+    Grayscale,
+    Rainbow,
+}
+
+impl std::str::FromStr for Palette {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "green" => Ok(Palette::Green),
+            "heat" => Ok(Palette::Heat),
+            "grayscale" => Ok(Palette::Grayscale),
+            "rainbow" => Ok(Palette::Rainbow),
+            _ => Err(format!("Unknown palette {}", name)),
+        }
+    }
+}
+
+// Picks an (r, g, b) colour for a cell from its distance to the root, using the book's intensity scale
+pub fn distance_colour(palette: Palette, distance: u32, max_distance: u32) -> (u8, u8, u8) {
+    let intensity = if max_distance == 0 {
+        1.0
+    } else {
+        (max_distance - distance.min(max_distance)) as f32 / max_distance as f32
+    };
+    let dark = (255. * intensity) as u8;
+    let bright = (128. + 127. * intensity) as u8;
+    match palette {
+        Palette::Green => (dark, bright, dark),
+        Palette::Heat => (
+            (255. * intensity) as u8,
+            (96. * (1. - (2. * intensity - 1.).abs())) as u8,
+            (255. * (1. - intensity)) as u8,
+        ),
+        Palette::Grayscale => {
+            let gray = (255. * (1. - 0.8 * intensity)) as u8;
+            (gray, gray, gray)
+        }
+        Palette::Rainbow => {
+            // sweep the hue from red at the root through to violet at the far end
+            let hue = 300. * (1. - intensity);
+            let sector = hue / 60.;
+            let x = 1. - (sector % 2. - 1.).abs();
+            let (r, g, b) = match sector as u32 {
+                0 => (1., x, 0.),
+                1 => (x, 1., 0.),
+                2 => (0., 1., x),
+                3 => (0., x, 1.),
+                _ => (x, 0., 1.),
+            };
+            ((255. * r) as u8, (255. * g) as u8, (255. * b) as u8)
+        }
+    }
+}
+
+// Parses colours written like "#ff8800", or one of the basic HTML colour names
+pub fn parse_colour(colour: &str) -> Result<(u8, u8, u8), String> {
+    let named = match colour.to_lowercase().as_str() {
+        "black" => Some((0, 0, 0)),
+        "white" => Some((255, 255, 255)),
+        "gray" | "grey" => Some((128, 128, 128)),
+        "silver" => Some((192, 192, 192)),
+        "red" => Some((255, 0, 0)),
+        "maroon" => Some((128, 0, 0)),
+        "orange" => Some((255, 165, 0)),
+        "yellow" => Some((255, 255, 0)),
+        "olive" => Some((128, 128, 0)),
+        "lime" => Some((0, 255, 0)),
+        "green" => Some((0, 128, 0)),
+        "teal" => Some((0, 128, 128)),
+        "aqua" | "cyan" => Some((0, 255, 255)),
+        "blue" => Some((0, 0, 255)),
+        "navy" => Some((0, 0, 128)),
+        "purple" => Some((128, 0, 128)),
+        "fuchsia" | "magenta" => Some((255, 0, 255)),
+        _ => None,
+    };
+    if let Some(rgb) = named {
+        return Ok(rgb);
+    }
+    let hex = colour.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|chr| chr.is_ascii_hexdigit()) {
+        return Err(format!(
+            "Colour {} should look like #rrggbb or be a name like white",
+            colour
+        ));
+    }
+    let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap();
+    Ok((channel(0), channel(2), channel(4)))
+}
+
+#[derive(Debug, Clone)]
+pub struct PathStyle {
+    pub colour: (u8, u8, u8),
+    pub width: f32,
+    pub start_colour: (u8, u8, u8),
+    pub goal_colour: (u8, u8, u8),
+}
+
+// the colours come from the classic theme
+impl Default for PathStyle {
+    fn default() -> Self {
+        let classic = theme::Theme::default();
+        Self {
+            colour: classic.path_colour,
+            width: 4.,
+            start_colour: classic.start_colour,
+            goal_colour: classic.goal_colour,
+        }
+    }
+}
+
+pub fn cell_centre(coords: (i32, i32), cell_size: i32) -> (f32, f32) {
+    let half = cell_size as f32 / 2.;
+    (
+        (coords.1 * cell_size + cell_size) as f32 + half,
+        (coords.0 * cell_size + cell_size) as f32 + half,
+    )
+}
+
+fn solid(colour: (u8, u8, u8)) -> raqote::Source<'static> {
+    raqote::Source::Solid(raqote::SolidSource {
+        r: colour.0,
+        g: colour.1,
+        b: colour.2,
+        a: 0xff,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Wall {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+impl Wall {
+    pub fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self { x1, y1, x2, y2 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineCap {
+    Round,
+    Square,
+    Butt,
+}
+
+impl std::str::FromStr for LineCap {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "round" => Ok(LineCap::Round),
+            "square" => Ok(LineCap::Square),
+            "butt" => Ok(LineCap::Butt),
+            _ => Err(format!("Unknown line cap {}", name)),
+        }
+    }
+}
+
+impl std::fmt::Display for LineCap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LineCap::Round => write!(f, "round"),
+            LineCap::Square => write!(f, "square"),
+            LineCap::Butt => write!(f, "butt"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WallStyle {
+    pub width: f32,
+    pub cap: LineCap,
+    pub colour: (u8, u8, u8),
+    pub alpha: u8,
+}
+
+impl Default for WallStyle {
+    fn default() -> Self {
+        let classic = theme::Theme::default();
+        Self {
+            width: 2.,
+            cap: LineCap::Round,
+            colour: classic.wall_colour,
+            alpha: classic.wall_alpha,
+        }
+    }
+}
+
+// we pad each cell by an extra cell_size to keep it off from the edges
+pub fn cell_box(cll: &cell::Cell, cell_size: i32) -> BoxCoords {
+    BoxCoords {
         x1: (cll.column * cell_size + cell_size) as f32,
         x2: ((cll.column + 1) * cell_size + cell_size) as f32,
         y1: (cll.row * cell_size + cell_size) as f32,
         y2: ((cll.row + 1) * cell_size + cell_size) as f32,
-    };
+    }
+}
+
+// Every cell draws its own north and east walls, plus its west and south walls on the boundary
+pub fn cell_walls(cll: &cell::Cell, cell_size: i32) -> Vec<Wall> {
+    let coords = cell_box(cll, cell_size);
+    let mut walls = vec![];
 
     if cll.west.is_none() {
-        pb.move_to(coords.x1, coords.y1);
-        pb.line_to(coords.x1, coords.y2);
+        walls.push(Wall {
+            x1: coords.x1,
+            y1: coords.y1,
+            x2: coords.x1,
+            y2: coords.y2,
+        });
     }
     if cll.south.is_none() {
-        pb.move_to(coords.x1, coords.y2);
-        pb.line_to(coords.x2, coords.y2);
-    }
-    if cll.east.is_none() {
-        pb.move_to(coords.x2, coords.y2);
-        pb.line_to(coords.x2, coords.y1);
-    }
-    if cll.north.is_none() {
-        pb.move_to(coords.x2, coords.y1);
-        pb.line_to(coords.x1, coords.y1);
+        walls.push(Wall {
+            x1: coords.x1,
+            y1: coords.y2,
+            x2: coords.x2,
+            y2: coords.y2,
+        });
     }
     if !cll.direction_has_link(cell::Direction::North) {
-        pb.move_to(coords.x1, coords.y1);
-        pb.line_to(coords.x2, coords.y1);
+        walls.push(Wall {
+            x1: coords.x1,
+            y1: coords.y1,
+            x2: coords.x2,
+            y2: coords.y1,
+        });
     }
     if !cll.direction_has_link(cell::Direction::East) {
-        pb.move_to(coords.x2, coords.y2);
-        pb.line_to(coords.x2, coords.y1);
-    }
-
-    let path = pb.finish();
-
-    dt.stroke(
-        &path,
-        &raqote::Source::Solid(raqote::SolidSource {
-            r: 0x0,
-            g: 0x0,
-            b: 0x0,
-            a: 0x99,
-        }),
-        &raqote::StrokeStyle {
-            cap: raqote::LineCap::Round,
-            join: raqote::LineJoin::Round,
-            width: 2.,
-            miter_limit: 1.,
-            dash_array: vec![],
-            dash_offset: 0.,
-        },
-        &raqote::DrawOptions::new(),
-    );
-
-    dt
+        walls.push(Wall {
+            x1: coords.x2,
+            y1: coords.y2,
+            x2: coords.x2,
+            y2: coords.y1,
+        });
+    }
+    walls
+}
+
+// With an inset, each cell is a smaller box joined to its linked neighbors by corridors
+pub fn cell_walls_inset(cll: &cell::Cell, cell_size: i32, inset: f32) -> Vec<Wall> {
+    let coords = cell_box(cll, cell_size);
+    let (x1, x2, x3, x4) = (coords.x1, coords.x1 + inset, coords.x2 - inset, coords.x2);
+    let (y1, y2, y3, y4) = (coords.y1, coords.y1 + inset, coords.y2 - inset, coords.y2);
+    let mut walls = vec![];
+
+    if cll.direction_has_link(cell::Direction::North) {
+        walls.push(Wall::new(x2, y1, x2, y2));
+        walls.push(Wall::new(x3, y1, x3, y2));
+    } else {
+        walls.push(Wall::new(x2, y2, x3, y2));
+    }
+    if cll.direction_has_link(cell::Direction::South) {
+        walls.push(Wall::new(x2, y3, x2, y4));
+        walls.push(Wall::new(x3, y3, x3, y4));
+    } else {
+        walls.push(Wall::new(x2, y3, x3, y3));
+    }
+    if cll.direction_has_link(cell::Direction::West) {
+        walls.push(Wall::new(x1, y2, x2, y2));
+        walls.push(Wall::new(x1, y3, x2, y3));
+    } else {
+        walls.push(Wall::new(x2, y2, x2, y3));
+    }
+    if cll.direction_has_link(cell::Direction::East) {
+        walls.push(Wall::new(x3, y2, x4, y2));
+        walls.push(Wall::new(x3, y3, x4, y3));
+    } else {
+        walls.push(Wall::new(x3, y2, x3, y3));
+    }
+    walls
+}
+
+// The floor of an inset cell: its inner box plus a corridor out to each linked neighbor
+pub fn cell_floor_inset(cll: &cell::Cell, cell_size: i32, inset: f32) -> Vec<BoxCoords> {
+    let coords = cell_box(cll, cell_size);
+    let inner = BoxCoords {
+        x1: coords.x1 + inset,
+        x2: coords.x2 - inset,
+        y1: coords.y1 + inset,
+        y2: coords.y2 - inset,
+    };
+    let mut floor = vec![];
+    if cll.direction_has_link(cell::Direction::North) {
+        floor.push(BoxCoords {
+            y1: coords.y1,
+            y2: inner.y1,
+            ..inner
+        });
+    }
+    if cll.direction_has_link(cell::Direction::South) {
+        floor.push(BoxCoords {
+            y1: inner.y2,
+            y2: coords.y2,
+            ..inner
+        });
+    }
+    if cll.direction_has_link(cell::Direction::West) {
+        floor.push(BoxCoords {
+            x1: coords.x1,
+            x2: inner.x1,
+            ..inner
+        });
+    }
+    if cll.direction_has_link(cell::Direction::East) {
+        floor.push(BoxCoords {
+            x1: inner.x2,
+            x2: coords.x2,
+            ..inner
+        });
+    }
+    floor.push(inner);
+    floor
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    Start,
+    Goal,
+}
+
+// Each output format implements this, and each kind of grid walks its cells through it once
+pub trait Renderer {
+    fn begin(&mut self, rows: i32, columns: i32);
+    fn fill_cell(&mut self, cll: &cell::Cell, distance: u32, colour: (u8, u8, u8));
+    fn draw_wall(&mut self, wall: &Wall);
+    fn draw_path(&mut self, path: &[(i32, i32)]);
+    fn draw_marker(&mut self, coords: (i32, i32), marker: Marker);
+    fn finish(&mut self, filename: &str) -> Result<(), String>;
+    fn cell_size(&self) -> i32;
+    // how far each cell is shrunk inside its box, in the same units as the cell size
+    fn inset(&self) -> f32 {
+        0.
+    }
+}
+
+// Cell backgrounds go down first so the walls are drawn on top of them, then the path
+pub fn render_cells<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    cells: &[&cell::Cell],
+    rows: i32,
+    columns: i32,
+    distances: Option<&HashMap<(i32, i32), u32>>,
+    palette: Palette,
+    path: Option<&[(i32, i32)]>,
+) {
+    renderer.begin(rows, columns);
+    if let Some(dist) = distances {
+        let max_distance = dist.values().copied().max().unwrap_or(0);
+        for cll in cells.iter() {
+            if let Some(distance) = dist.get(&cll.coords()) {
+                let colour = distance_colour(palette, *distance, max_distance);
+                renderer.fill_cell(cll, *distance, colour);
+            }
+        }
+    }
+    draw_walls(renderer, cells);
+    if let Some(path) = path {
+        draw_route(renderer, path);
+    }
+}
+
+pub fn draw_walls<R: Renderer + ?Sized>(renderer: &mut R, cells: &[&cell::Cell]) {
+    let cell_size = renderer.cell_size();
+    let inset = renderer.inset();
+    for cll in cells.iter() {
+        let walls = if inset > 0. {
+            cell_walls_inset(cll, cell_size, inset)
+        } else {
+            cell_walls(cll, cell_size)
+        };
+        for wall in walls {
+            renderer.draw_wall(&wall);
+        }
+    }
+}
+
+// The path with a marker at each end
+pub fn draw_route<R: Renderer + ?Sized>(renderer: &mut R, path: &[(i32, i32)]) {
+    if !path.is_empty() {
+        renderer.draw_path(path);
+        renderer.draw_marker(path[0], Marker::Start);
+        renderer.draw_marker(path[path.len() - 1], Marker::Goal);
+    }
+}
+
+pub struct PngRenderer {
+    cell_size: i32,
+    inset: f32,
+    wall_style: WallStyle,
+    path_style: PathStyle,
+    // transparent when unset
+    background: Option<(u8, u8, u8)>,
+    dt: raqote::DrawTarget,
+    walls: raqote::PathBuilder,
+}
+
+impl PngRenderer {
+    // the inset is a fraction of the cell size: 0.0 draws plain walls
+    pub fn new(cell_size: i32, inset: f32, wall_style: WallStyle, path_style: PathStyle) -> Self {
+        Self {
+            cell_size,
+            inset: inset * cell_size as f32,
+            wall_style,
+            path_style,
+            background: None,
+            dt: raqote::DrawTarget::new(1, 1),
+            walls: raqote::PathBuilder::new(),
+        }
+    }
+
+    pub fn set_background(&mut self, background: Option<(u8, u8, u8)>) {
+        self.background = background;
+    }
+
+    pub fn draw_target(&mut self) -> &raqote::DrawTarget {
+        self.stroke_walls();
+        &self.dt
+    }
+
+    // walls are collected into one path so overlapping ends don't darken the corners
+    fn stroke_walls(&mut self) {
+        let walls = std::mem::replace(&mut self.walls, raqote::PathBuilder::new()).finish();
+        self.dt.stroke(
+            &walls,
+            &raqote::Source::Solid(raqote::SolidSource::from_unpremultiplied_argb(
+                self.wall_style.alpha,
+                self.wall_style.colour.0,
+                self.wall_style.colour.1,
+                self.wall_style.colour.2,
+            )),
+            &raqote::StrokeStyle {
+                cap: match self.wall_style.cap {
+                    LineCap::Round => raqote::LineCap::Round,
+                    LineCap::Square => raqote::LineCap::Square,
+                    LineCap::Butt => raqote::LineCap::Butt,
+                },
+                join: raqote::LineJoin::Round,
+                width: self.wall_style.width,
+                miter_limit: 1.,
+                dash_array: vec![],
+                dash_offset: 0.,
+            },
+            &raqote::DrawOptions::new(),
+        );
+    }
+}
+
+impl Renderer for PngRenderer {
+    fn begin(&mut self, rows: i32, columns: i32) {
+        // we pad it an extra cell_size on each side to keep it off from the edges
+        self.dt = raqote::DrawTarget::new(
+            self.cell_size * columns + self.cell_size * 2,
+            self.cell_size * rows + self.cell_size * 2,
+        );
+        if let Some((r, g, b)) = self.background {
+            self.dt
+                .clear(raqote::SolidSource::from_unpremultiplied_argb(
+                    0xff, r, g, b,
+                ));
+        }
+        self.walls = raqote::PathBuilder::new();
+    }
+
+    fn fill_cell(&mut self, cll: &cell::Cell, _distance: u32, colour: (u8, u8, u8)) {
+        let floor = if self.inset > 0. {
+            cell_floor_inset(cll, self.cell_size, self.inset)
+        } else {
+            vec![cell_box(cll, self.cell_size)]
+        };
+        for coords in floor {
+            self.dt.fill_rect(
+                coords.x1,
+                coords.y1,
+                coords.x2 - coords.x1,
+                coords.y2 - coords.y1,
+                &solid(colour),
+                &raqote::DrawOptions::new(),
+            );
+        }
+    }
+
+    fn draw_wall(&mut self, wall: &Wall) {
+        self.walls.move_to(wall.x1, wall.y1);
+        self.walls.line_to(wall.x2, wall.y2);
+    }
+
+    fn draw_path(&mut self, path: &[(i32, i32)]) {
+        self.stroke_walls();
+        let mut pb = raqote::PathBuilder::new();
+        for (idx, coords) in path.iter().enumerate() {
+            let (x, y) = cell_centre(*coords, self.cell_size);
+            if idx == 0 {
+                pb.move_to(x, y);
+            } else {
+                pb.line_to(x, y);
+            }
+        }
+        self.dt.stroke(
+            &pb.finish(),
+            &solid(self.path_style.colour),
+            &raqote::StrokeStyle {
+                cap: raqote::LineCap::Round,
+                join: raqote::LineJoin::Round,
+                width: self.path_style.width,
+                miter_limit: 1.,
+                dash_array: vec![],
+                dash_offset: 0.,
+            },
+            &raqote::DrawOptions::new(),
+        );
+    }
+
+    fn draw_marker(&mut self, coords: (i32, i32), marker: Marker) {
+        self.stroke_walls();
+        let colour = match marker {
+            Marker::Start => self.path_style.start_colour,
+            Marker::Goal => self.path_style.goal_colour,
+        };
+        let (x, y) = cell_centre(coords, self.cell_size);
+        let mut pb = raqote::PathBuilder::new();
+        pb.arc(
+            x,
+            y,
+            self.cell_size as f32 / 4.,
+            0.,
+            2. * std::f32::consts::PI,
+        );
+        self.dt
+            .fill(&pb.finish(), &solid(colour), &raqote::DrawOptions::new());
+    }
+
+    fn finish(&mut self, filename: &str) -> Result<(), String> {
+        self.stroke_walls();
+        self.dt
+            .write_png(filename)
+            .map_err(|err| format!("Failed writing file {}", err))
+    }
+
+    fn cell_size(&self) -> i32 {
+        self.cell_size
+    }
+
+    fn inset(&self) -> f32 {
+        self.inset
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cell_walls, cell_walls_inset, distance_colour, parse_colour, Palette};
+    use crate::hash_grid::HashGrid;
+
+    #[test]
+    fn test_inset_walls() {
+        let mut hgrid = HashGrid::new(1, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        let cll = hgrid.get_item((0, 0)).unwrap();
+        assert_eq!(cell_walls(cll, 10).len(), 3);
+        let walls = cell_walls_inset(cll, 10, 2.);
+        // three sides of the inner box plus both sides of the corridor to the east
+        assert_eq!(walls.len(), 5);
+        assert!(walls.iter().all(|wall| wall.x1 >= 12. && wall.x2 <= 20.));
+    }
+
+    #[test]
+    fn test_distance_colour() {
+        let ends = |palette: Palette| {
+            (
+                distance_colour(palette, 0, 10),
+                distance_colour(palette, 10, 10),
+            )
+        };
+        assert_eq!(ends(Palette::Green), ((255, 255, 255), (0, 128, 0)));
+        assert_eq!(ends(Palette::Heat), ((255, 0, 0), (0, 0, 255)));
+        assert_eq!(ends(Palette::Grayscale), ((50, 50, 50), (255, 255, 255)));
+        assert_eq!(ends(Palette::Rainbow), ((255, 0, 0), (255, 0, 255)));
+        // the heat map peaks in green halfway out
+        assert_eq!(distance_colour(Palette::Heat, 5, 10), (127, 96, 127));
+        // distances past the furthest cell and maps with only the root stay in range
+        assert_eq!(
+            distance_colour(Palette::Green, 12, 10),
+            distance_colour(Palette::Green, 10, 10)
+        );
+        assert_eq!(distance_colour(Palette::Green, 0, 0), (255, 255, 255));
+    }
+
+    #[test]
+    fn test_parse_palette() {
+        assert_eq!("rainbow".parse::<Palette>(), Ok(Palette::Rainbow));
+        assert!("sepia".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn test_parse_colour() {
+        assert_eq!(parse_colour("#ff8800"), Ok((255, 136, 0)));
+        assert_eq!(parse_colour("White"), Ok((255, 255, 255)));
+        assert!(parse_colour("#ff88").is_err());
+        assert!(parse_colour("#gg8800").is_err());
+        assert!(parse_colour("\"/><x").is_err());
+    }
 }
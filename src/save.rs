@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ascii;
+use crate::binary;
+use crate::distances;
+use crate::grid;
+use crate::hash_grid;
+use crate::png_import;
+
+pub const VERSION: u32 = 1;
+
+// Above this many cells mazes are saved in the binary format unless the name ends in .json
+pub const LARGE_MAZE: usize = 10_000;
+
+// How the maze was made, so it can be made again
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub algorithm: Option<String>,
+    pub seed: Option<u64>,
+    pub braid: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedCell {
+    pub row: i32,
+    pub column: i32,
+    pub links: Vec<(i32, i32)>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedDistances {
+    pub root: (i32, i32),
+    // (row, column, distance)
+    pub cells: Vec<(i32, i32, u32)>,
+}
+
+// JSON object keys have to be strings, so the cell and distance maps are saved as sorted lists
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeFile {
+    pub version: u32,
+    pub rows: i32,
+    pub columns: i32,
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    pub cells: Vec<SavedCell>,
+    #[serde(default)]
+    pub distances: Option<SavedDistances>,
+    #[serde(default)]
+    pub path: Option<Vec<(i32, i32)>>,
+}
+
+fn saved_cells<'a>(cells: impl Iterator<Item = &'a crate::cell::Cell>) -> Vec<SavedCell> {
+    cells
+        .map(|cll| {
+            let mut links: Vec<(i32, i32)> = cll.links.iter().copied().collect();
+            links.sort_unstable();
+            SavedCell {
+                row: cll.row,
+                column: cll.column,
+                links,
+            }
+        })
+        .collect()
+}
+
+impl MazeFile {
+    pub fn from_hashgrid(hgrid: &hash_grid::HashGrid, metadata: Metadata) -> Self {
+        let distances = hgrid.distances().map(|dm| {
+            let mut cells: Vec<(i32, i32, u32)> = dm
+                .map
+                .iter()
+                .map(|(coords, distance)| (coords.0, coords.1, *distance))
+                .collect();
+            cells.sort_unstable();
+            SavedDistances {
+                root: dm.root(),
+                cells,
+            }
+        });
+        Self {
+            version: VERSION,
+            rows: hgrid.rows,
+            columns: hgrid.columns,
+            metadata,
+            cells: saved_cells(hgrid.cells().into_iter()),
+            distances,
+            path: hgrid.path().map(|path| path.to_vec()),
+        }
+    }
+
+    // Grid cells are saved with their links as they are, one-sided or not
+    pub fn from_grid(some_grid: &grid::Grid, metadata: Metadata) -> Self {
+        Self {
+            version: VERSION,
+            rows: some_grid.rows,
+            columns: some_grid.columns,
+            metadata,
+            cells: saved_cells(some_grid.iter()),
+            distances: None,
+            path: None,
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.version != VERSION {
+            return Err(format!("Unsupported maze file version {}", self.version));
+        }
+        if self.rows <= 0 || self.columns <= 0 {
+            return Err(format!("Invalid dimensions {}x{}", self.rows, self.columns));
+        }
+        let in_bounds = |coords: (i32, i32)| {
+            coords.0 >= 0 && coords.0 < self.rows && coords.1 >= 0 && coords.1 < self.columns
+        };
+        for cll in self.cells.iter() {
+            if !in_bounds((cll.row, cll.column)) {
+                return Err(format!(
+                    "Cell {:?} is outside the grid",
+                    (cll.row, cll.column)
+                ));
+            }
+            for link in cll.links.iter() {
+                let apart = (cll.row - link.0).abs() + (cll.column - link.1).abs();
+                if !in_bounds(*link) || apart != 1 {
+                    return Err(format!(
+                        "Cell {:?} links to {:?} which isn't a neighbor",
+                        (cll.row, cll.column),
+                        link
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_hashgrid(&self) -> Result<hash_grid::HashGrid, String> {
+        self.check()?;
+        let mut hgrid = hash_grid::HashGrid::new(self.rows, self.columns);
+        for cll in self.cells.iter() {
+            for link in cll.links.iter() {
+                hgrid.link_cells((cll.row, cll.column), *link);
+            }
+        }
+        if let Some(saved) = self.distances.as_ref() {
+            let map: HashMap<(i32, i32), u32> = saved
+                .cells
+                .iter()
+                .map(|(row, column, distance)| ((*row, *column), *distance))
+                .collect();
+            hgrid.set_distances(Some(distances::DistanceMap::new(saved.root, map)));
+        }
+        hgrid.set_path(self.path.clone());
+        Ok(hgrid)
+    }
+
+    pub fn to_grid(&self) -> Result<grid::Grid, String> {
+        self.check()?;
+        let mut some_grid = grid::Grid::new(self.rows, self.columns);
+        for saved in self.cells.iter() {
+            let mut cll = some_grid
+                .get_item((saved.row, saved.column))
+                .unwrap()
+                .clone();
+            for link in saved.links.iter() {
+                cll.link(*link);
+            }
+            some_grid.replace_cell(cll).map_err(|err| err.to_string())?;
+        }
+        Ok(some_grid)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| format!("Failed serializing maze {}", err))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|err| format!("Invalid maze file {}", err))
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        fs::write(filename, self.to_json()?).map_err(|err| format!("Failed writing file {}", err))
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(filename).map_err(|err| format!("Failed reading file {}", err))?;
+        Self::from_json(&text)
+    }
+}
+
+// .json and .maze pick the format, otherwise it depends on the size of the maze.
+// The binary format only keeps the walls and the metadata, without the distances or path.
+pub fn save_maze(
+    hgrid: &hash_grid::HashGrid,
+    metadata: &Metadata,
+    filename: &str,
+) -> Result<(), String> {
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    let use_binary = match extension.as_deref() {
+        Some("json") => false,
+        Some("maze") => true,
+        _ => hgrid.len() > LARGE_MAZE,
+    };
+    if use_binary {
+        binary::save(
+            hgrid,
+            metadata.seed,
+            metadata.braid,
+            metadata.algorithm.as_deref(),
+            filename,
+        )
+    } else {
+        MazeFile::from_hashgrid(hgrid, metadata.clone()).save(filename)
+    }
+}
+
+// Loads JSON, binary, an ASCII drawing or a PNG, telling them apart by their first bytes.
+// PNGs are expected to be drawn the way main draws them.
+pub fn load_maze(filename: &str) -> Result<(hash_grid::HashGrid, Metadata), String> {
+    let bytes = fs::read(filename).map_err(|err| format!("Failed reading file {}", err))?;
+    if bytes.starts_with(binary::MAGIC) {
+        let (hgrid, header) = binary::read(&bytes)?;
+        let metadata = Metadata {
+            algorithm: header.algorithm,
+            seed: header.seed,
+            braid: header.braid,
+        };
+        return Ok((hgrid, metadata));
+    }
+    if bytes.starts_with(b"\x89PNG") {
+        let hgrid = png_import::from_drawn_png(filename)?;
+        return Ok((hgrid, Metadata::default()));
+    }
+    let text = std::str::from_utf8(&bytes).map_err(|err| format!("Invalid maze file {}", err))?;
+    if !text.trim_start().starts_with('{') {
+        return Ok((ascii::parse(text)?, Metadata::default()));
+    }
+    let saved = MazeFile::from_json(text)?;
+    Ok((saved.to_hashgrid()?, saved.metadata))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_maze, save_maze, MazeFile, Metadata};
+    use crate::algorithms;
+    use crate::grid::Grid;
+
+    #[test]
+    fn test_hashgrid_round_trip() {
+        let mut hgrid = algorithms::generate("aldous-broder", 6, 7).unwrap();
+        hgrid.build_breadcrumbs_to_longest();
+        let metadata = Metadata {
+            algorithm: Some("aldous-broder".to_string()),
+            seed: Some(99),
+            braid: None,
+        };
+        let saved = MazeFile::from_hashgrid(&hgrid, metadata.clone());
+        let loaded = MazeFile::from_json(&saved.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.metadata, metadata);
+
+        let restored = loaded.to_hashgrid().unwrap();
+        assert_eq!(format!("{}", restored), format!("{}", hgrid));
+        assert_eq!(restored.path(), hgrid.path());
+        assert_eq!(restored.distances(), hgrid.distances());
+    }
+
+    #[test]
+    fn test_binary_keeps_algorithm_and_seed() {
+        let hgrid = algorithms::generate("wilsons", 5, 6).unwrap();
+        let metadata = Metadata {
+            algorithm: Some("wilsons".to_string()),
+            seed: Some(12),
+            braid: Some(0.25),
+        };
+        let fname = std::env::temp_dir().join("mazes-save-test.maze");
+        let fname = fname.to_str().unwrap();
+        save_maze(&hgrid, &metadata, fname).unwrap();
+        let (loaded, loaded_metadata) = load_maze(fname).unwrap();
+        assert_eq!(format!("{}", loaded), format!("{}", hgrid));
+        assert_eq!(loaded_metadata, metadata);
+    }
+
+    #[test]
+    fn test_grid_keeps_one_sided_links() {
+        let raw = algorithms::binary_tree(&Grid::new(4, 4));
+        let saved = MazeFile::from_grid(&raw, Metadata::default());
+        let restored = saved.to_grid().unwrap();
+        for (before, after) in raw.iter().zip(restored.iter()) {
+            assert_eq!(before.links, after.links);
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_links() {
+        let text = r#"{"version": 1, "rows": 2, "columns": 2, "cells": [{"row": 0, "column": 0, "links": [[1, 1]]}]}"#;
+        let saved = MazeFile::from_json(text).unwrap();
+        assert!(saved.to_hashgrid().is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::distances;
+use crate::hash_grid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Solver {
+    // expands the whole frontier one distance at a time
+    Bfs,
+    // expands the open cell with the lowest distance plus manhattan distance to the goal
+    AStar,
+}
+
+impl std::str::FromStr for Solver {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "bfs" => Ok(Solver::Bfs),
+            "astar" | "a-star" => Ok(Solver::AStar),
+            _ => Err(format!("Unknown solver {}", name)),
+        }
+    }
+}
+
+// A step of the search: the cells waiting to be expanded, and the cells expanded since the last
+// step. The closed set is the newly closed cells of every step so far.
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchStep {
+    pub open: Vec<(i32, i32)>,
+    pub newly_closed: Vec<(i32, i32)>,
+}
+
+pub fn search_steps(
+    solver: Solver,
+    hgrid: &hash_grid::HashGrid,
+    start: (i32, i32),
+    goal: (i32, i32),
+) -> Vec<SearchStep> {
+    match solver {
+        Solver::Bfs => bfs_steps(hgrid, start, goal),
+        Solver::AStar => astar_steps(hgrid, start, goal),
+    }
+}
+
+// The BFS in DistanceMap already visits cells level by level, so each distance up to the goal's
+// is one step with that level open and the level before it newly closed
+pub fn bfs_steps(
+    hgrid: &hash_grid::HashGrid,
+    start: (i32, i32),
+    goal: (i32, i32),
+) -> Vec<SearchStep> {
+    let dm = distances::DistanceMap::from_hashgrid(start, hgrid);
+    let last = dm
+        .map
+        .get(&goal)
+        .copied()
+        .unwrap_or_else(|| dm.map.values().copied().max().unwrap_or(0));
+    let cells = hgrid.cells();
+    (0..=last)
+        .map(|level| {
+            let mut step = SearchStep::default();
+            for cll in cells.iter() {
+                match dm.map.get(&cll.coords()) {
+                    Some(distance) if *distance + 1 == level => {
+                        step.newly_closed.push(cll.coords())
+                    }
+                    Some(distance) if *distance == level => step.open.push(cll.coords()),
+                    _ => (),
+                }
+            }
+            step
+        })
+        .collect()
+}
+
+fn manhattan(from: (i32, i32), to: (i32, i32)) -> u32 {
+    ((from.0 - to.0).abs() + (from.1 - to.1).abs()) as u32
+}
+
+// One step per expanded cell, stopping once the goal comes off the open set
+pub fn astar_steps(
+    hgrid: &hash_grid::HashGrid,
+    start: (i32, i32),
+    goal: (i32, i32),
+) -> Vec<SearchStep> {
+    let mut steps = vec![];
+    let mut costs: HashMap<(i32, i32), u32> = HashMap::new();
+    let mut closed: HashSet<(i32, i32)> = HashSet::new();
+    // ties on the estimate go to the cell furthest along
+    let mut open = BinaryHeap::new();
+    costs.insert(start, 0);
+    open.push(Reverse((manhattan(start, goal), Reverse(0), start)));
+
+    while let Some(Reverse((_, Reverse(cost), current))) = open.pop() {
+        if closed.contains(&current) || costs.get(&current) != Some(&cost) {
+            continue;
+        }
+        closed.insert(current);
+        if current != goal {
+            let cll = hgrid.get_item(current).unwrap();
+            let mut links: Vec<&(i32, i32)> = cll.links.iter().collect();
+            links.sort_unstable();
+            for key in links {
+                let next_cost = cost + 1;
+                let cheaper = match costs.get(key) {
+                    Some(old) => next_cost < *old,
+                    None => true,
+                };
+                if !closed.contains(key) && cheaper {
+                    costs.insert(*key, next_cost);
+                    open.push(Reverse((
+                        next_cost + manhattan(*key, goal),
+                        Reverse(next_cost),
+                        *key,
+                    )));
+                }
+            }
+        }
+        let mut waiting: Vec<(i32, i32)> = costs
+            .keys()
+            .filter(|coords| !closed.contains(coords))
+            .copied()
+            .collect();
+        waiting.sort_unstable();
+        steps.push(SearchStep {
+            open: waiting,
+            newly_closed: vec![current],
+        });
+        if current == goal {
+            break;
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod test {
+    use super::{astar_steps, bfs_steps, SearchStep};
+    use crate::algorithms;
+    use crate::distances::DistanceMap;
+    use crate::hash_grid::HashGrid;
+
+    fn closed(steps: &[SearchStep]) -> Vec<(i32, i32)> {
+        steps
+            .iter()
+            .flat_map(|step| step.newly_closed.iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_bfs_levels() {
+        let hgrid = algorithms::generate("aldous-broder", 6, 6).unwrap();
+        let goal_distance = *DistanceMap::from_hashgrid((0, 0), &hgrid)
+            .map
+            .get(&(5, 5))
+            .unwrap();
+        let steps = bfs_steps(&hgrid, (0, 0), (5, 5));
+        assert_eq!(steps.len() as u32, goal_distance + 1);
+        assert_eq!(steps[0].open, vec![(0, 0)]);
+        assert!(steps[0].newly_closed.is_empty());
+        assert_eq!(steps[1].newly_closed, vec![(0, 0)]);
+        assert!(steps.last().unwrap().open.contains(&(5, 5)));
+    }
+
+    #[test]
+    fn test_astar_heads_for_goal() {
+        // an open room: A* walks straight to the goal while BFS spreads out in every direction
+        let mut hgrid = HashGrid::new(5, 5);
+        for rownum in 0..5 {
+            for colnum in 0..5 {
+                if colnum < 4 {
+                    hgrid.link_cells((rownum, colnum), (rownum, colnum + 1));
+                }
+                if rownum < 4 {
+                    hgrid.link_cells((rownum, colnum), (rownum + 1, colnum));
+                }
+            }
+        }
+        let steps = astar_steps(&hgrid, (0, 0), (0, 4));
+        let astar_closed = closed(&steps);
+        assert_eq!(astar_closed, vec![(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]);
+
+        let bfs = bfs_steps(&hgrid, (0, 0), (0, 4));
+        assert!(closed(&bfs).len() > astar_closed.len());
+    }
+}
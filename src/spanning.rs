@@ -0,0 +1,265 @@
+use std::fs;
+
+use petgraph::algo::connected_components;
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::EdgeRef;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+// Spanning trees of any undirected graph, so mazes can be made on road networks or meshes
+// as well as grids. Every node is kept in the tree, under the same index it had in the graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TreeAlgorithm {
+    // wanders at random, keeping the edge it used to reach each new node
+    RandomWalk,
+    // joins loop-erased random walks onto the tree, uniform like the random walk but quicker
+    Wilsons,
+    // takes edges in random order unless they'd close a loop
+    Kruskal,
+}
+
+impl std::str::FromStr for TreeAlgorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "random-walk" | "aldous-broder" => Ok(TreeAlgorithm::RandomWalk),
+            "wilsons" => Ok(TreeAlgorithm::Wilsons),
+            "kruskal" => Ok(TreeAlgorithm::Kruskal),
+            _ => Err(format!("Unknown spanning tree algorithm {}", name)),
+        }
+    }
+}
+
+pub fn spanning_tree<N: Clone, E: Clone>(
+    graph: &UnGraph<N, E>,
+    algorithm: TreeAlgorithm,
+) -> Result<UnGraph<N, E>, String> {
+    spanning_tree_with_rng(graph, algorithm, &mut thread_rng())
+}
+
+// The tree's edges are added in the order they were picked, so replaying them shows the carving
+pub fn spanning_tree_with_rng<N: Clone, E: Clone, R: Rng + ?Sized>(
+    graph: &UnGraph<N, E>,
+    algorithm: TreeAlgorithm,
+    rng: &mut R,
+) -> Result<UnGraph<N, E>, String> {
+    if connected_components(graph) > 1 {
+        return Err("The graph isn't connected, so it has no spanning tree".to_string());
+    }
+    let picked = if graph.node_count() == 0 {
+        vec![]
+    } else {
+        match algorithm {
+            TreeAlgorithm::RandomWalk => random_walk(graph, rng),
+            TreeAlgorithm::Wilsons => wilsons(graph, rng),
+            TreeAlgorithm::Kruskal => kruskal(graph, rng),
+        }
+    };
+
+    let mut tree = UnGraph::with_capacity(graph.node_count(), picked.len());
+    for node in graph.node_indices() {
+        tree.add_node(graph[node].clone());
+    }
+    for edge in picked {
+        let (source, target) = graph.edge_endpoints(edge).unwrap();
+        tree.add_edge(source, target, graph[edge].clone());
+    }
+    Ok(tree)
+}
+
+// The edges leaving a node, in a fixed order and without self loops
+fn exits<N, E>(graph: &UnGraph<N, E>, node: NodeIndex) -> Vec<(EdgeIndex, NodeIndex)> {
+    let mut exits: Vec<(EdgeIndex, NodeIndex)> = graph
+        .edges(node)
+        .map(|edge| {
+            let other = if edge.source() == node {
+                edge.target()
+            } else {
+                edge.source()
+            };
+            (edge.id(), other)
+        })
+        .filter(|(_, other)| *other != node)
+        .collect();
+    exits.sort_unstable();
+    exits
+}
+
+fn random_walk<N, E, R: Rng + ?Sized>(graph: &UnGraph<N, E>, rng: &mut R) -> Vec<EdgeIndex> {
+    let mut visited = vec![false; graph.node_count()];
+    let mut current = NodeIndex::new(rng.gen_range(0..graph.node_count()));
+    visited[current.index()] = true;
+    let mut unvisited = graph.node_count() - 1;
+    let mut picked = Vec::with_capacity(unvisited);
+    while unvisited > 0 {
+        let (edge, next) = *exits(graph, current).choose(rng).unwrap();
+        if !visited[next.index()] {
+            visited[next.index()] = true;
+            unvisited -= 1;
+            picked.push(edge);
+        }
+        current = next;
+    }
+    picked
+}
+
+fn wilsons<N, E, R: Rng + ?Sized>(graph: &UnGraph<N, E>, rng: &mut R) -> Vec<EdgeIndex> {
+    let count = graph.node_count();
+    let mut in_tree = vec![false; count];
+    in_tree[rng.gen_range(0..count)] = true;
+    // the step each node last took on the current walk, which erases loops as it's overwritten
+    let mut step: Vec<Option<(EdgeIndex, NodeIndex)>> = vec![None; count];
+    let mut picked = Vec::with_capacity(count - 1);
+    let mut remaining: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|node| !in_tree[node.index()])
+        .collect();
+    remaining.shuffle(rng);
+    for start in remaining {
+        let mut current = start;
+        while !in_tree[current.index()] {
+            let exit = *exits(graph, current).choose(rng).unwrap();
+            step[current.index()] = Some(exit);
+            current = exit.1;
+        }
+        current = start;
+        while !in_tree[current.index()] {
+            let (edge, next) = step[current.index()].unwrap();
+            in_tree[current.index()] = true;
+            picked.push(edge);
+            current = next;
+        }
+    }
+    picked
+}
+
+fn kruskal<N, E, R: Rng + ?Sized>(graph: &UnGraph<N, E>, rng: &mut R) -> Vec<EdgeIndex> {
+    let mut edges: Vec<EdgeIndex> = graph.edge_indices().collect();
+    edges.shuffle(rng);
+    let mut sets = UnionFind::new(graph.node_count());
+    edges
+        .into_iter()
+        .filter(|edge| {
+            let (source, target) = graph.edge_endpoints(*edge).unwrap();
+            sets.union(source.index(), target.index())
+        })
+        .collect()
+}
+
+// Draws the passages of a tree between the positions given for its nodes, for graphs that
+// aren't laid out on a grid. Nodes without a position are left out, along with their edges.
+pub fn to_svg<N, E>(
+    tree: &UnGraph<N, E>,
+    position: impl Fn(&N) -> Option<(f32, f32)>,
+    passage_width: f32,
+    filename: &str,
+) -> Result<(), String> {
+    let points: Vec<Option<(f32, f32)>> = tree
+        .node_indices()
+        .map(|node| position(&tree[node]))
+        .collect();
+    let placed: Vec<(f32, f32)> = points.iter().flatten().copied().collect();
+    if placed.is_empty() {
+        return Err("None of the nodes have a position".to_string());
+    }
+    let margin = passage_width;
+    let min_x = placed.iter().map(|point| point.0).fold(f32::MAX, f32::min) - margin;
+    let min_y = placed.iter().map(|point| point.1).fold(f32::MAX, f32::min) - margin;
+    let max_x = placed.iter().map(|point| point.0).fold(f32::MIN, f32::max) + margin;
+    let max_y = placed.iter().map(|point| point.1).fold(f32::MIN, f32::max) + margin;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        min_x,
+        min_y,
+        max_x - min_x,
+        max_y - min_y
+    );
+    svg.push_str(&format!(
+        "<g stroke=\"#000000\" stroke-width=\"{}\" stroke-linecap=\"round\">\n",
+        passage_width
+    ));
+    for edge in tree.edge_references() {
+        if let (Some(first), Some(second)) =
+            (points[edge.source().index()], points[edge.target().index()])
+        {
+            svg.push_str(&format!(
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>\n",
+                first.0, first.1, second.0, second.1
+            ));
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    fs::write(filename, svg).map_err(|err| format!("Failed writing file {}", err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{spanning_tree_with_rng, to_svg, TreeAlgorithm};
+    use petgraph::algo::{connected_components, is_cyclic_undirected};
+    use petgraph::graph::UnGraph;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // a wheel: a ring of spokes around a hub, which no grid can hold
+    fn wheel(spokes: u32) -> UnGraph<u32, ()> {
+        let mut graph = UnGraph::new_undirected();
+        let hub = graph.add_node(0);
+        let rim: Vec<_> = (1..=spokes).map(|idx| graph.add_node(idx)).collect();
+        for (idx, node) in rim.iter().enumerate() {
+            graph.add_edge(hub, *node, ());
+            graph.add_edge(*node, rim[(idx + 1) % rim.len()], ());
+        }
+        graph
+    }
+
+    #[test]
+    fn test_spanning_trees() {
+        let graph = wheel(12);
+        for algorithm in [
+            TreeAlgorithm::RandomWalk,
+            TreeAlgorithm::Wilsons,
+            TreeAlgorithm::Kruskal,
+        ] {
+            let mut rng = StdRng::seed_from_u64(5);
+            let tree = spanning_tree_with_rng(&graph, algorithm, &mut rng).unwrap();
+            assert_eq!(tree.node_count(), 13, "{:?}", algorithm);
+            assert_eq!(tree.edge_count(), 12, "{:?}", algorithm);
+            assert_eq!(connected_components(&tree), 1, "{:?}", algorithm);
+            assert!(!is_cyclic_undirected(&tree), "{:?}", algorithm);
+            for edge in tree.edge_indices() {
+                let (source, target) = tree.edge_endpoints(edge).unwrap();
+                assert!(graph.contains_edge(source, target));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_disconnected_graph() {
+        let mut graph = wheel(4);
+        graph.add_node(99);
+        let mut rng = StdRng::seed_from_u64(5);
+        assert!(spanning_tree_with_rng(&graph, TreeAlgorithm::Kruskal, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_to_svg_places_nodes() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let tree = spanning_tree_with_rng(&wheel(8), TreeAlgorithm::Wilsons, &mut rng).unwrap();
+        let fname = std::env::temp_dir().join("mazes-spanning-test.svg");
+        let fname = fname.to_str().unwrap();
+        // the hub sits in the middle with the rim on a circle around it
+        let position = |idx: &u32| {
+            let angle = *idx as f32 * std::f32::consts::PI / 4.;
+            let radius = if *idx == 0 { 0. } else { 50. };
+            Some((radius * angle.cos(), radius * angle.sin()))
+        };
+        to_svg(&tree, position, 6., fname).unwrap();
+        let svg = std::fs::read_to_string(fname).unwrap();
+        assert_eq!(svg.matches("<line ").count(), 8);
+        std::fs::remove_file(fname).unwrap();
+        assert!(to_svg(&tree, |_| None, 6., fname).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs;
+
+use crate::cell;
+use crate::render;
+
+#[derive(Debug)]
+pub struct SvgStyle {
+    pub stroke_width: f32,
+    pub wall_colour: String,
+    pub wall_opacity: f32,
+    pub line_cap: render::LineCap,
+    pub background: Option<String>,
+}
+
+impl Default for SvgStyle {
+    // matches the stroke used for PNG output
+    fn default() -> Self {
+        Self {
+            stroke_width: 2.0,
+            wall_colour: "#000000".to_string(),
+            wall_opacity: 0.6,
+            line_cap: render::LineCap::Round,
+            background: None,
+        }
+    }
+}
+
+pub fn hex(colour: (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.0, colour.1, colour.2)
+}
+
+// The style's colours are free text, so they're escaped before going into an attribute.
+// The Tiled map writer uses this for its tileset name too.
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub struct SvgRenderer {
+    cell_size: i32,
+    style: SvgStyle,
+    path_style: render::PathStyle,
+    document: String,
+    // walls share their stroke attributes in a single group
+    in_walls: bool,
+}
+
+impl SvgRenderer {
+    pub fn new(cell_size: i32, style: SvgStyle, path_style: render::PathStyle) -> Self {
+        Self {
+            cell_size,
+            style,
+            path_style,
+            document: String::new(),
+            in_walls: false,
+        }
+    }
+
+    fn close_walls(&mut self) {
+        if self.in_walls {
+            self.document.push_str("  </g>\n");
+            self.in_walls = false;
+        }
+    }
+
+    pub fn document(&mut self) -> String {
+        self.close_walls();
+        format!("{}</svg>\n", self.document)
+    }
+}
+
+impl render::Renderer for SvgRenderer {
+    fn begin(&mut self, rows: i32, columns: i32) {
+        let width = self.cell_size * columns + self.cell_size * 2;
+        let height = self.cell_size * rows + self.cell_size * 2;
+        self.in_walls = false;
+        self.document = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = width,
+            h = height
+        );
+        if let Some(background) = self.style.background.as_ref() {
+            self.document.push_str(&format!(
+                "  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+                escape(background)
+            ));
+        }
+    }
+
+    fn fill_cell(&mut self, cll: &cell::Cell, _distance: u32, colour: (u8, u8, u8)) {
+        self.close_walls();
+        let coords = render::cell_box(cll, self.cell_size);
+        self.document.push_str(&format!(
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+            coords.x1,
+            coords.y1,
+            coords.x2 - coords.x1,
+            coords.y2 - coords.y1,
+            hex(colour)
+        ));
+    }
+
+    fn draw_wall(&mut self, wall: &render::Wall) {
+        if !self.in_walls {
+            self.document.push_str(&format!(
+                "  <g stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\" stroke-linecap=\"{}\" fill=\"none\">\n",
+                escape(&self.style.wall_colour),
+                self.style.wall_opacity,
+                self.style.stroke_width,
+                self.style.line_cap
+            ));
+            self.in_walls = true;
+        }
+        self.document.push_str(&format!(
+            "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>\n",
+            wall.x1, wall.y1, wall.x2, wall.y2
+        ));
+    }
+
+    fn draw_path(&mut self, path: &[(i32, i32)]) {
+        self.close_walls();
+        let points: Vec<String> = path
+            .iter()
+            .map(|coords| {
+                let (x, y) = render::cell_centre(*coords, self.cell_size);
+                format!("{},{}", x, y)
+            })
+            .collect();
+        self.document.push_str(&format!(
+            "  <polyline points=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\" fill=\"none\"/>\n",
+            points.join(" "),
+            hex(self.path_style.colour),
+            self.path_style.width
+        ));
+    }
+
+    fn draw_marker(&mut self, coords: (i32, i32), marker: render::Marker) {
+        self.close_walls();
+        let colour = match marker {
+            render::Marker::Start => self.path_style.start_colour,
+            render::Marker::Goal => self.path_style.goal_colour,
+        };
+        let (x, y) = render::cell_centre(coords, self.cell_size);
+        self.document.push_str(&format!(
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n",
+            x,
+            y,
+            self.cell_size as f32 / 4.,
+            hex(colour)
+        ));
+    }
+
+    fn finish(&mut self, filename: &str) -> Result<(), String> {
+        fs::write(filename, self.document()).map_err(|err| format!("Failed writing file {}", err))
+    }
+
+    fn cell_size(&self) -> i32 {
+        self.cell_size
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SvgRenderer, SvgStyle};
+    use crate::hash_grid::HashGrid;
+    use crate::render;
+
+    #[test]
+    fn test_svg_walls() {
+        let mut hgrid = HashGrid::new(1, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        let mut renderer = SvgRenderer::new(10, SvgStyle::default(), render::PathStyle::default());
+        hgrid.render(&mut renderer, render::Palette::Green);
+        let doc = renderer.document();
+        assert!(doc.starts_with("<svg"));
+        // two north walls, two south walls, the west wall and the east wall
+        assert_eq!(doc.matches("<line").count(), 6);
+        assert!(doc.contains("<line x1=\"10\" y1=\"10\" x2=\"10\" y2=\"20\"/>"));
+        assert!(!doc.contains("<line x1=\"20\" y1=\"20\" x2=\"20\" y2=\"10\"/>"));
+        assert!(doc.ends_with("  </g>\n</svg>\n"));
+    }
+
+    #[test]
+    fn test_svg_escapes_colours() {
+        let style = SvgStyle {
+            wall_colour: "red\" onload=\"alert(1)".to_string(),
+            background: Some("<white>".to_string()),
+            ..SvgStyle::default()
+        };
+        let mut renderer = SvgRenderer::new(10, style, render::PathStyle::default());
+        HashGrid::new(1, 1).render(&mut renderer, render::Palette::Green);
+        let doc = renderer.document();
+        assert!(doc.contains("stroke=\"red&quot; onload=&quot;alert(1)\""));
+        assert!(doc.contains("fill=\"&lt;white&gt;\""));
+    }
+}
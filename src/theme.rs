@@ -0,0 +1,177 @@
+use serde::Deserialize;
+
+use crate::render;
+
+pub const THEMES: [&str; 4] = ["classic", "blueprint", "night", "print"];
+
+// Colours shared by every renderer, so one name or file restyles all the outputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    // transparent when unset
+    pub background: Option<(u8, u8, u8)>,
+    pub wall_colour: (u8, u8, u8),
+    pub wall_alpha: u8,
+    pub path_colour: (u8, u8, u8),
+    pub start_colour: (u8, u8, u8),
+    pub goal_colour: (u8, u8, u8),
+    pub palette: render::Palette,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: None,
+            wall_colour: (0x0, 0x0, 0x0),
+            wall_alpha: 0x99,
+            path_colour: (0xcc, 0x22, 0x22),
+            start_colour: (0x22, 0x99, 0x22),
+            goal_colour: (0x22, 0x22, 0xcc),
+            palette: render::Palette::Green,
+        }
+    }
+}
+
+// Every field is optional so a file only has to list what it changes from its base theme
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    background: Option<String>,
+    wall_colour: Option<String>,
+    wall_alpha: Option<u8>,
+    path_colour: Option<String>,
+    start_colour: Option<String>,
+    goal_colour: Option<String>,
+    palette: Option<String>,
+}
+
+impl Theme {
+    pub fn named(name: &str) -> Result<Self, String> {
+        let classic = Self::default();
+        match name {
+            "classic" => Ok(classic),
+            "blueprint" => Ok(Self {
+                background: Some((0x1f, 0x4e, 0x8c)),
+                wall_colour: (0xff, 0xff, 0xff),
+                wall_alpha: 0xe6,
+                path_colour: (0xff, 0xd2, 0x3f),
+                start_colour: (0x7e, 0xe0, 0x81),
+                goal_colour: (0xff, 0x6b, 0x6b),
+                palette: render::Palette::Grayscale,
+            }),
+            "night" => Ok(Self {
+                background: Some((0x11, 0x11, 0x11)),
+                wall_colour: (0xe0, 0xe0, 0xe0),
+                wall_alpha: 0xff,
+                path_colour: (0xff, 0x55, 0x55),
+                start_colour: (0x50, 0xfa, 0x7b),
+                goal_colour: (0x8b, 0xe9, 0xfd),
+                palette: render::Palette::Rainbow,
+            }),
+            // solid black on white for printing
+            "print" => Ok(Self {
+                background: Some((0xff, 0xff, 0xff)),
+                wall_alpha: 0xff,
+                path_colour: (0x55, 0x55, 0x55),
+                start_colour: (0x0, 0x0, 0x0),
+                goal_colour: (0x0, 0x0, 0x0),
+                palette: render::Palette::Grayscale,
+                ..classic
+            }),
+            _ => Err(format!("Unknown theme {}", name)),
+        }
+    }
+
+    // Parses a theme from TOML, or JSON when `json` is set
+    pub fn parse(text: &str, json: bool) -> Result<Self, String> {
+        let file: ThemeFile = if json {
+            serde_json::from_str(text).map_err(|err| format!("Invalid theme {}", err))?
+        } else {
+            toml::from_str(text).map_err(|err| format!("Invalid theme {}", err))?
+        };
+        let mut theme = Self::named(file.base.as_deref().unwrap_or("classic"))?;
+        if let Some(background) = file.background {
+            theme.background = Some(render::parse_colour(&background)?);
+        }
+        if let Some(colour) = file.wall_colour {
+            theme.wall_colour = render::parse_colour(&colour)?;
+        }
+        if let Some(alpha) = file.wall_alpha {
+            theme.wall_alpha = alpha;
+        }
+        if let Some(colour) = file.path_colour {
+            theme.path_colour = render::parse_colour(&colour)?;
+        }
+        if let Some(colour) = file.start_colour {
+            theme.start_colour = render::parse_colour(&colour)?;
+        }
+        if let Some(colour) = file.goal_colour {
+            theme.goal_colour = render::parse_colour(&colour)?;
+        }
+        if let Some(palette) = file.palette {
+            theme.palette = palette.parse()?;
+        }
+        Ok(theme)
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename)
+            .map_err(|err| format!("Failed reading theme {}", err))?;
+        Self::parse(&text, filename.to_lowercase().ends_with(".json"))
+    }
+
+    // A built-in theme's name, or else the path to a theme file
+    pub fn select(name: &str) -> Result<Self, String> {
+        if THEMES.contains(&name) {
+            Self::named(name)
+        } else {
+            Self::load(name)
+        }
+    }
+
+    pub fn wall_style(&self) -> render::WallStyle {
+        render::WallStyle {
+            colour: self.wall_colour,
+            alpha: self.wall_alpha,
+            ..render::WallStyle::default()
+        }
+    }
+
+    pub fn path_style(&self) -> render::PathStyle {
+        render::PathStyle {
+            colour: self.path_colour,
+            start_colour: self.start_colour,
+            goal_colour: self.goal_colour,
+            ..render::PathStyle::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Theme, THEMES};
+    use crate::render;
+
+    #[test]
+    fn test_builtin_themes() {
+        for name in THEMES.iter() {
+            assert!(Theme::named(name).is_ok(), "{}", name);
+        }
+        assert!(Theme::named("missing").is_err());
+    }
+
+    #[test]
+    fn test_parse_overrides_base() {
+        let toml = "base = \"night\"\nwall_colour = \"#336699\"\npalette = \"heat\"\n";
+        let json = r##"{"base": "night", "wall_colour": "#336699", "palette": "heat"}"##;
+        for theme in [Theme::parse(toml, false), Theme::parse(json, true)] {
+            let theme = theme.unwrap();
+            assert_eq!(theme.wall_colour, (0x33, 0x66, 0x99));
+            assert_eq!(theme.palette, render::Palette::Heat);
+            // everything else comes from the base
+            assert_eq!(theme.background, Theme::named("night").unwrap().background);
+        }
+        assert!(Theme::parse("wall_colour = \"bleu\"", false).is_err());
+        assert!(Theme::parse("wall_color = \"#000000\"", false).is_err());
+    }
+}
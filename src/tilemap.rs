@@ -0,0 +1,390 @@
+use serde::Serialize;
+
+use crate::cell;
+use crate::hash_grid;
+use crate::svg;
+
+// Tiled's map format version, and the release of the editor that writes it
+const TILED_VERSION: &str = "1.10";
+const TILED_EDITOR_VERSION: &str = "1.10.2";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tile {
+    Wall,
+    Floor,
+    Start,
+    Goal,
+    Breadcrumb,
+}
+
+// Global tile IDs as Tiled numbers them, where 0 is an empty tile and the tileset starts at 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileIds {
+    pub wall: u32,
+    pub floor: u32,
+    pub start: u32,
+    pub goal: u32,
+    pub breadcrumb: u32,
+}
+
+impl Default for TileIds {
+    fn default() -> Self {
+        Self {
+            wall: 1,
+            floor: 2,
+            start: 3,
+            goal: 4,
+            breadcrumb: 5,
+        }
+    }
+}
+
+// Parses "wall,floor,start,goal,breadcrumb", where any left off keep their defaults
+impl std::str::FromStr for TileIds {
+    type Err = String;
+
+    fn from_str(ids: &str) -> Result<Self, Self::Err> {
+        let parsed = ids
+            .split(',')
+            .map(|id| id.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|err| format!("Invalid tile ids {} {}", ids, err))?;
+        if parsed.len() > 5 {
+            return Err(format!(
+                "Expected at most 5 tile ids but found {}",
+                parsed.len()
+            ));
+        }
+        let mut tile_ids = Self::default();
+        let mut fields = [
+            &mut tile_ids.wall,
+            &mut tile_ids.floor,
+            &mut tile_ids.start,
+            &mut tile_ids.goal,
+            &mut tile_ids.breadcrumb,
+        ];
+        for (field, id) in fields.iter_mut().zip(parsed) {
+            **field = id;
+        }
+        Ok(tile_ids)
+    }
+}
+
+impl TileIds {
+    pub fn id(&self, tile: Tile) -> u32 {
+        match tile {
+            Tile::Wall => self.wall,
+            Tile::Floor => self.floor,
+            Tile::Start => self.start,
+            Tile::Goal => self.goal,
+            Tile::Breadcrumb => self.breadcrumb,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilemapOptions {
+    // tiles along each side of a cell, counting the wall it shares with the next cell
+    pub cell_tiles: usize,
+    pub tile_size: u32,
+    pub ids: TileIds,
+    // mark the start, goal and breadcrumbs from the maze's distance map
+    pub marks: bool,
+    pub tileset: String,
+}
+
+impl Default for TilemapOptions {
+    fn default() -> Self {
+        Self {
+            cell_tiles: 2,
+            tile_size: 16,
+            ids: TileIds::default(),
+            marks: false,
+            tileset: "maze.tsx".to_string(),
+        }
+    }
+}
+
+// Each cell's floor is cell_tiles - 1 tiles square, with a one tile wall on every side that it
+// shares with its neighbours. Passages knock out the wall between two cells' floors.
+pub fn blocks(hgrid: &hash_grid::HashGrid, cell_tiles: usize) -> Result<Vec<Vec<Tile>>, String> {
+    if cell_tiles < 2 {
+        return Err(format!("Cells need at least 2 tiles, not {}", cell_tiles));
+    }
+    let (rows, columns) = (hgrid.rows as usize, hgrid.columns as usize);
+    let mut tiles = vec![vec![Tile::Wall; columns * cell_tiles + 1]; rows * cell_tiles + 1];
+    for cll in hgrid.cells() {
+        fill(&mut tiles, cell_tiles, cll.coords(), None, Tile::Floor);
+        for way in [cell::Direction::East, cell::Direction::South] {
+            if cll.direction_has_link(way) {
+                fill(&mut tiles, cell_tiles, cll.coords(), Some(way), Tile::Floor);
+            }
+        }
+    }
+    Ok(tiles)
+}
+
+// Fills a cell's floor, or the passage on one side of it
+fn fill(
+    tiles: &mut [Vec<Tile>],
+    cell_tiles: usize,
+    coords: (i32, i32),
+    passage: Option<cell::Direction>,
+    tile: Tile,
+) {
+    let top = coords.0 as usize * cell_tiles + 1;
+    let left = coords.1 as usize * cell_tiles + 1;
+    let floor = cell_tiles - 1;
+    let (rows, columns) = match passage {
+        None => (top..top + floor, left..left + floor),
+        Some(cell::Direction::North) => (top - 1..top, left..left + floor),
+        Some(cell::Direction::South) => (top + floor..top + floor + 1, left..left + floor),
+        Some(cell::Direction::West) => (top..top + floor, left - 1..left),
+        Some(cell::Direction::East) => (top..top + floor, left + floor..left + floor + 1),
+    };
+    for row in rows {
+        for column in columns.clone() {
+            tiles[row][column] = tile;
+        }
+    }
+}
+
+// The breadcrumb path when there is one, running from the distance map's root. With only a
+// distance map the goal is the furthest cell from the root, and there are no breadcrumbs.
+fn mark(tiles: &mut [Vec<Tile>], hgrid: &hash_grid::HashGrid, cell_tiles: usize) {
+    let distances = match hgrid.distances() {
+        Some(distances) => distances,
+        None => return,
+    };
+    let start = distances.root();
+    let goal = match hgrid.path() {
+        Some(path) => {
+            for (idx, coords) in path.iter().enumerate() {
+                fill(tiles, cell_tiles, *coords, None, Tile::Breadcrumb);
+                if let Some(next) = path.get(idx + 1) {
+                    let way = hgrid
+                        .get_item(*coords)
+                        .and_then(|cll| cll.neighbor_direction(*next));
+                    if let Some(way) = way {
+                        fill(tiles, cell_tiles, *coords, Some(way), Tile::Breadcrumb);
+                    }
+                }
+            }
+            // the path runs from the root, but may have been loaded either way round
+            if path.first() == Some(&start) {
+                path.last().copied()
+            } else {
+                path.first().copied()
+            }
+        }
+        None => distances
+            .map
+            .iter()
+            .max_by_key(|(coords, distance)| (**distance, std::cmp::Reverse(**coords)))
+            .map(|(coords, _)| *coords),
+    };
+    fill(tiles, cell_tiles, start, None, Tile::Start);
+    if let Some(goal) = goal.filter(|goal| *goal != start) {
+        fill(tiles, cell_tiles, goal, None, Tile::Goal);
+    }
+}
+
+type Tiles = Vec<Vec<u32>>;
+
+// The global tile IDs row by row, as both formats store them
+fn layer_data(hgrid: &hash_grid::HashGrid, options: &TilemapOptions) -> Result<Tiles, String> {
+    let mut tiles = blocks(hgrid, options.cell_tiles)?;
+    if options.marks {
+        mark(&mut tiles, hgrid, options.cell_tiles);
+    }
+    Ok(tiles
+        .iter()
+        .map(|row| row.iter().map(|tile| options.ids.id(*tile)).collect())
+        .collect())
+}
+
+#[derive(Serialize)]
+struct TiledTileset {
+    firstgid: u32,
+    source: String,
+}
+
+#[derive(Serialize)]
+struct TiledLayer {
+    id: u32,
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    width: usize,
+    height: usize,
+    x: i32,
+    y: i32,
+    opacity: f32,
+    visible: bool,
+    data: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct TiledMap {
+    #[serde(rename = "type")]
+    kind: String,
+    version: String,
+    tiledversion: String,
+    orientation: String,
+    renderorder: String,
+    width: usize,
+    height: usize,
+    tilewidth: u32,
+    tileheight: u32,
+    infinite: bool,
+    nextlayerid: u32,
+    nextobjectid: u32,
+    layers: Vec<TiledLayer>,
+    tilesets: Vec<TiledTileset>,
+}
+
+pub fn to_tiled_json(
+    hgrid: &hash_grid::HashGrid,
+    options: &TilemapOptions,
+) -> Result<String, String> {
+    let data = layer_data(hgrid, options)?;
+    let (width, height) = (data[0].len(), data.len());
+    let map = TiledMap {
+        kind: "map".to_string(),
+        version: TILED_VERSION.to_string(),
+        tiledversion: TILED_EDITOR_VERSION.to_string(),
+        orientation: "orthogonal".to_string(),
+        renderorder: "right-down".to_string(),
+        width,
+        height,
+        tilewidth: options.tile_size,
+        tileheight: options.tile_size,
+        infinite: false,
+        nextlayerid: 2,
+        nextobjectid: 1,
+        layers: vec![TiledLayer {
+            id: 1,
+            name: "maze".to_string(),
+            kind: "tilelayer".to_string(),
+            width,
+            height,
+            x: 0,
+            y: 0,
+            opacity: 1.,
+            visible: true,
+            data: data.into_iter().flatten().collect(),
+        }],
+        tilesets: vec![TiledTileset {
+            firstgid: 1,
+            source: options.tileset.clone(),
+        }],
+    };
+    serde_json::to_string_pretty(&map).map_err(|err| format!("Failed serializing map {}", err))
+}
+
+pub fn to_tmx(hgrid: &hash_grid::HashGrid, options: &TilemapOptions) -> Result<String, String> {
+    let data = layer_data(hgrid, options)?;
+    let (width, height) = (data[0].len(), data.len());
+    let rows: Vec<String> = data
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        })
+        .collect();
+    let mut tmx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    tmx.push_str(&format!(
+        "<map version=\"{}\" tiledversion=\"{}\" orientation=\"orthogonal\" \
+         renderorder=\"right-down\" width=\"{}\" height=\"{}\" tilewidth=\"{}\" \
+         tileheight=\"{}\" infinite=\"0\" nextlayerid=\"2\" nextobjectid=\"1\">\n",
+        TILED_VERSION, TILED_EDITOR_VERSION, width, height, options.tile_size, options.tile_size
+    ));
+    tmx.push_str(&format!(
+        " <tileset firstgid=\"1\" source=\"{}\"/>\n",
+        svg::escape(&options.tileset)
+    ));
+    tmx.push_str(&format!(
+        " <layer id=\"1\" name=\"maze\" width=\"{}\" height=\"{}\">\n  <data encoding=\"csv\">\n",
+        width, height
+    ));
+    // every row but the last ends in a comma, as Tiled writes them
+    tmx.push_str(&rows.join(",\n"));
+    tmx.push_str("\n</data>\n </layer>\n</map>\n");
+    Ok(tmx)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{blocks, to_tiled_json, to_tmx, Tile, TileIds, TilemapOptions};
+    use crate::algorithms;
+    use crate::hash_grid::HashGrid;
+
+    #[test]
+    fn test_blocks() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        hgrid.link_cells((0, 1), (1, 1));
+        hgrid.link_cells((1, 1), (1, 0));
+        let drawn: Vec<String> = blocks(&hgrid, 2)
+            .unwrap()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| if *tile == Tile::Wall { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(drawn, ["#####", "#...#", "###.#", "#...#", "#####"]);
+        assert_eq!(blocks(&hgrid, 3).unwrap().len(), 7);
+        assert!(blocks(&hgrid, 1).is_err());
+    }
+
+    #[test]
+    fn test_tiled_formats() {
+        let mut hgrid = algorithms::generate("sidewinder", 3, 4).unwrap();
+        hgrid.build_breadcrumbs_to_longest();
+        let options = TilemapOptions {
+            cell_tiles: 3,
+            ids: "7,8,9,10,11".parse().unwrap(),
+            marks: true,
+            ..TilemapOptions::default()
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_tiled_json(&hgrid, &options).unwrap()).unwrap();
+        assert_eq!(
+            (json["width"].as_u64(), json["height"].as_u64()),
+            (Some(13), Some(10))
+        );
+        let data: Vec<u64> = json["layers"][0]["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_u64().unwrap())
+            .collect();
+        assert_eq!(data.len(), 130);
+        // each marked cell has a floor of 2x2 tiles
+        assert_eq!(data.iter().filter(|id| **id == 9).count(), 4);
+        assert_eq!(data.iter().filter(|id| **id == 10).count(), 4);
+        assert!(data.contains(&11));
+
+        let tmx = to_tmx(&hgrid, &options).unwrap();
+        assert!(tmx.contains("width=\"13\" height=\"10\""));
+        let csv = tmx.split("<data encoding=\"csv\">\n").nth(1).unwrap();
+        let csv = csv.split("\n</data>").next().unwrap();
+        assert_eq!(csv.lines().count(), 10);
+        assert_eq!(csv.split(',').count(), 130);
+
+        let options = TilemapOptions {
+            tileset: "<maze> & \"tiles\".tsx".to_string(),
+            ..TilemapOptions::default()
+        };
+        let tmx = to_tmx(&hgrid, &options).unwrap();
+        assert!(tmx.contains("source=\"&lt;maze&gt; &amp; &quot;tiles&quot;.tsx\""));
+
+        assert_eq!("4,5".parse::<TileIds>().unwrap().start, 3);
+        assert!("1,x".parse::<TileIds>().is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use crate::grid;
+use crate::hash_grid;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Violation {
+    AsymmetricLink((i32, i32), (i32, i32)),
+    NotNeighbor((i32, i32), (i32, i32)),
+    Disconnected { reachable: usize, cells: usize },
+    NotPerfect { passages: usize, expected: usize },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Violation::AsymmetricLink(from, to) => write!(
+                f,
+                "{:?} links to {:?} but {:?} doesn't link back",
+                from, to, to
+            ),
+            Violation::NotNeighbor(from, to) => {
+                write!(f, "{:?} links to {:?} which isn't a neighbor", from, to)
+            }
+            Violation::Disconnected { reachable, cells } => write!(
+                f,
+                "only {} of {} cells are reachable from (0, 0)",
+                reachable, cells
+            ),
+            Violation::NotPerfect { passages, expected } => write!(
+                f,
+                "a perfect maze should have {} passages but found {}",
+                expected, passages
+            ),
+        }
+    }
+}
+
+// Checks the invariants every maze should hold. With `perfect`, also checks there are no loops.
+pub fn validate(hgrid: &hash_grid::HashGrid, perfect: bool) -> Vec<Violation> {
+    let mut violations = vec![];
+    let mut passages: HashSet<((i32, i32), (i32, i32))> = HashSet::new();
+
+    for rownum in 0..hgrid.rows {
+        for colnum in 0..hgrid.columns {
+            let cll = match hgrid.get_item((rownum, colnum)) {
+                Some(cll) => cll,
+                None => continue,
+            };
+            let mut links: Vec<&(i32, i32)> = cll.links.iter().collect();
+            links.sort_unstable();
+            for key in links {
+                if !cll.neighbors().contains(&key) {
+                    violations.push(Violation::NotNeighbor(cll.coords(), *key));
+                    continue;
+                }
+                let linked_back = hgrid
+                    .get_item(*key)
+                    .map(|other| other.links.contains(&cll.coords()))
+                    .unwrap_or(false);
+                if !linked_back {
+                    violations.push(Violation::AsymmetricLink(cll.coords(), *key));
+                }
+                passages.insert((cll.coords().min(*key), cll.coords().max(*key)));
+            }
+        }
+    }
+
+    let cells = hgrid.len();
+    if cells > 0 {
+        let reachable = reachable_cells(hgrid);
+        if reachable != cells {
+            violations.push(Violation::Disconnected { reachable, cells });
+        }
+    }
+    if perfect && passages.len() + 1 != cells {
+        violations.push(Violation::NotPerfect {
+            passages: passages.len(),
+            expected: cells.saturating_sub(1),
+        });
+    }
+    violations
+}
+
+// Counts the cells reachable from (0, 0) along links between neighbors, skipping the broken links
+// reported above rather than following them out of the grid
+fn reachable_cells(hgrid: &hash_grid::HashGrid) -> usize {
+    let mut seen: HashSet<(i32, i32)> = HashSet::new();
+    let mut frontier: Vec<(i32, i32)> = vec![];
+    if hgrid.get_item((0, 0)).is_some() {
+        seen.insert((0, 0));
+        frontier.push((0, 0));
+    }
+    while let Some(coords) = frontier.pop() {
+        let cll = hgrid.get_item(coords).unwrap();
+        let neighbors = cll.neighbors();
+        for key in cll.links.iter() {
+            if neighbors.contains(&key) && hgrid.get_item(*key).is_some() && seen.insert(*key) {
+                frontier.push(*key);
+            }
+        }
+    }
+    seen.len()
+}
+
+pub fn validate_grid(some_grid: &grid::Grid, perfect: bool) -> Vec<Violation> {
+    let hgrid = hash_grid::HashGrid::from_cells(some_grid.each_row().cloned().collect());
+    validate(&hgrid, perfect)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate, validate_grid, Violation};
+    use crate::algorithms;
+    use crate::grid::Grid;
+    use crate::hash_grid::HashGrid;
+
+    #[test]
+    fn test_generated_mazes_are_valid() {
+        for algorithm in algorithms::ALGORITHMS.iter() {
+            let hgrid = algorithms::generate(algorithm, 8, 8).unwrap();
+            assert_eq!(validate(&hgrid, true), vec![], "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn test_one_sided_links() {
+        let raw = algorithms::binary_tree(&Grid::new(3, 3));
+        let violations = validate_grid(&raw, true);
+        assert!(violations
+            .iter()
+            .any(|violation| matches!(violation, Violation::AsymmetricLink(_, _))));
+    }
+
+    #[test]
+    fn test_non_neighbor_link() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        hgrid.link_cells((0, 1), (1, 1));
+        hgrid.link_cells((1, 1), (1, 0));
+        let mut cll = hgrid.get_item((0, 0)).unwrap().clone();
+        // add_neighbor turns anything that isn't adjacent into a link
+        cll.add_neighbor((1, 1));
+        hgrid.replace_cell(cll).unwrap();
+        let violations = validate(&hgrid, false);
+        assert!(violations.contains(&Violation::NotNeighbor((0, 0), (1, 1))));
+    }
+
+    #[test]
+    fn test_link_outside_grid() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        let mut cll = hgrid.get_item((0, 0)).unwrap().clone();
+        cll.link((5, 5));
+        hgrid.replace_cell(cll).unwrap();
+        let violations = validate(&hgrid, false);
+        assert!(violations.contains(&Violation::NotNeighbor((0, 0), (5, 5))));
+        assert!(violations.contains(&Violation::Disconnected {
+            reachable: 2,
+            cells: 4
+        }));
+    }
+
+    #[test]
+    fn test_disconnected_and_braided() {
+        let mut hgrid = HashGrid::new(2, 2);
+        hgrid.link_cells((0, 0), (0, 1));
+        assert!(validate(&hgrid, false).contains(&Violation::Disconnected {
+            reachable: 2,
+            cells: 4
+        }));
+        hgrid.link_cells((0, 1), (1, 1));
+        hgrid.link_cells((1, 1), (1, 0));
+        hgrid.link_cells((1, 0), (0, 0));
+        assert_eq!(validate(&hgrid, false), vec![]);
+        assert_eq!(
+            validate(&hgrid, true),
+            vec![Violation::NotPerfect {
+                passages: 4,
+                expected: 3
+            }]
+        );
+    }
+}